@@ -0,0 +1,116 @@
+/// Schedule of event banners, used to tell which banner a pull is made from
+use chrono::{FixedOffset, NaiveDateTime};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data_type::{Item, PoolKind, Pull, Rarity},
+    item_database::ItemDatabase,
+};
+
+/// Offset of the time zone versions are released in, UTC+8
+const UPDATE_OFFSET: i32 = 8 * 3600;
+
+/// The bundled schedule
+const BUILTIN_SCHEDULE: &str = include_str!("banner_schedule.json");
+
+lazy_static! {
+    static ref BUILTIN: BannerSchedule =
+        serde_json::from_str(BUILTIN_SCHEDULE).expect("内置卡池时间表已损坏");
+}
+
+/// An event banner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Banner {
+    /// version of the game such as `1.3`
+    pub version: String,
+    /// phase within the version, starting from 1
+    pub phase: u8,
+    /// type of the pool the banner runs in
+    pub kind: PoolKind,
+    pub name: String,
+    /// start of the banner. The first phase of a version starts with the update at the same
+    /// moment on every server, and its start is in UTC+8. Later phases start in server time
+    pub start: NaiveDateTime,
+    /// end of the banner, inclusive. The last phase of a version ends with the next update,
+    /// and its end is in UTC+8. Earlier phases end in server time
+    pub end: NaiveDateTime,
+    /// ids of the rate-up five-stars
+    pub five_stars: Vec<String>,
+    /// ids of the rate-up four-stars, empty if unknown
+    pub four_stars: Vec<String>,
+}
+
+impl Banner {
+    /// Ids of the rate-up items of `rarity`, empty if unknown
+    pub fn rate_ups(&self, rarity: Rarity) -> &[String] {
+        match rarity {
+            Rarity::Five => &self.five_stars,
+            Rarity::Four => &self.four_stars,
+            Rarity::Three => &[],
+        }
+    }
+
+    /// Whether `item` is one of the rate-up items. Items without id are looked up in
+    /// `database` by name
+    pub fn is_rate_up(&self, item: &Item, database: &ItemDatabase) -> bool {
+        let item_id = database
+            .get(item)
+            .map_or(item.item_id.as_str(), |info| info.item_id.as_str());
+        self.rate_ups(item.rarity)
+            .iter()
+            .any(|rate_up| rate_up == item_id)
+    }
+
+    /// Name of the banner along with its version and phase, such as `1.3 赤团开时（第3期）`
+    pub fn title(&self) -> String {
+        format!("{} {}（第{}期）", self.version, self.name, self.phase)
+    }
+}
+
+/// Event banners in chronological order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BannerSchedule {
+    pub banners: Vec<Banner>,
+}
+
+impl BannerSchedule {
+    /// The schedule bundled in the binary
+    pub fn builtin() -> &'static Self {
+        &BUILTIN
+    }
+
+    /// The banner `pull` is made from, `None` if the pull is not from an event wish or the
+    /// banner at its time is unknown. Bounds set by updates are compared in UTC+8, and the
+    /// others in the time zone of the server the pull is made on
+    pub fn banner_of(&self, pull: &Pull) -> Option<&Banner> {
+        let kind = pull.pool_kind()?;
+        let server_time = pull.time.naive_local();
+        let update_time = pull
+            .time
+            .with_timezone(&FixedOffset::east(UPDATE_OFFSET))
+            .naive_local();
+        self.banners.iter().find(|banner| {
+            let start = if banner.phase == 1 {
+                update_time
+            } else {
+                server_time
+            };
+            let end = if self.is_last_phase(banner) {
+                update_time
+            } else {
+                server_time
+            };
+            banner.kind == kind && banner.start <= start && end <= banner.end
+        })
+    }
+
+    /// Whether `banner` is the last of its kind in its version, ending with the next update
+    fn is_last_phase(&self, banner: &Banner) -> bool {
+        !self.banners.iter().any(|other| {
+            other.kind == banner.kind
+                && other.version == banner.version
+                && other.phase > banner.phase
+        })
+    }
+}
@@ -0,0 +1,155 @@
+/// Recording of API traffic, so that a problem can be reproduced after the authkey expires
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Context};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::client::{HttpResponse, Transport, TransportFuture};
+
+/// Placeholder of the authkey in recorded urls
+const REDACTED: &str = "REDACTED";
+
+/// What to do with the cassette file
+#[derive(Debug, Clone)]
+pub enum CassetteMode {
+    /// record every request and response to the file
+    Record(PathBuf),
+    /// serve requests from the file instead of the network
+    Replay(PathBuf),
+}
+
+/// A request along with its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// url of the request with the authkey redacted and the query sorted
+    pub url: String,
+    pub status: u16,
+    pub body: String,
+}
+
+/// Content of a cassette file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+/// A transport either recording the traffic of another transport to a cassette, or
+/// replaying it from the cassette
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    /// transport being recorded, `None` when replaying
+    inner: Option<Box<dyn Transport>>,
+    /// interactions recorded so far, or those not replayed yet
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl Cassette {
+    /// Open a cassette recording `inner`, or replacing it when replaying. A cassette to be
+    /// recorded starts empty
+    pub fn open(mode: &CassetteMode, inner: Box<dyn Transport>) -> anyhow::Result<Self> {
+        match mode {
+            CassetteMode::Record(path) => Ok(Self {
+                path: path.clone(),
+                inner: Some(inner),
+                interactions: Mutex::new(vec![]),
+            }),
+            CassetteMode::Replay(path) => {
+                let file = File::open(path)
+                    .with_context(|| format!("无法读取录制文件 {}", path.display()))?;
+                let tape: Tape = serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| format!("录制文件 {} 已损坏", path.display()))?;
+                Ok(Self {
+                    path: path.clone(),
+                    inner: None,
+                    interactions: Mutex::new(tape.interactions),
+                })
+            }
+        }
+    }
+
+    /// Take the recorded response of `url`. The same request made several times gets the
+    /// responses in the order they are recorded
+    fn replay(&self, url: &Url) -> anyhow::Result<HttpResponse> {
+        let key = redact(url);
+        let mut interactions = self.interactions.lock().unwrap();
+        let index = interactions
+            .iter()
+            .position(|interaction| interaction.url == key)
+            .ok_or_else(|| anyhow!("录制文件 {} 中没有请求: {}", self.path.display(), key))?;
+        let interaction = interactions.remove(index);
+        let status = StatusCode::from_u16(interaction.status)
+            .with_context(|| format!("录制文件 {} 已损坏", self.path.display()))?;
+        Ok(HttpResponse {
+            status,
+            body: interaction.body,
+        })
+    }
+
+    /// Record a response of `url`. The file is rewritten every time so that the traffic
+    /// before a failure is kept
+    fn record(&self, url: &Url, resp: &HttpResponse) -> anyhow::Result<()> {
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(Interaction {
+            url: redact(url),
+            status: resp.status.as_u16(),
+            body: resp.body.clone(),
+        });
+        let write = || -> anyhow::Result<()> {
+            let mut output = BufWriter::new(File::create(&self.path)?);
+            serde_json::to_writer_pretty(
+                &mut output,
+                &Tape {
+                    interactions: interactions.clone(),
+                },
+            )?;
+            output.flush()?;
+            Ok(())
+        };
+        write().with_context(|| format!("无法写入录制文件 {}", self.path.display()))
+    }
+}
+
+impl Transport for Cassette {
+    fn get<'a>(&'a self, url: &'a Url) -> TransportFuture<'a> {
+        Box::pin(async move {
+            match &self.inner {
+                Some(inner) => {
+                    let resp = inner.get(url).await?;
+                    self.record(url, &resp)?;
+                    Ok(resp)
+                }
+                None => self.replay(url),
+            }
+        })
+    }
+}
+
+/// Normalize `url` for the cassette, the authkey is redacted and the query is sorted since
+/// the order of query pairs is not stable
+pub fn redact(url: &Url) -> String {
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if key == "authkey" {
+                REDACTED.to_owned()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    query.sort();
+    let mut url = url.clone();
+    url.set_query(None);
+    if !query.is_empty() {
+        url.query_pairs_mut().extend_pairs(query);
+    }
+    url.to_string()
+}
@@ -0,0 +1,98 @@
+/// Progress of unfinished fetches, so that a later run continues from where the last one stops
+use std::{
+    fs::{create_dir_all, remove_file, rename, File},
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::data_type::Pull;
+
+/// Default location of checkpoints, relative to the current directory
+pub const DEFAULT_CHECKPOINT_DIR: &str = "gacha_checkpoint";
+
+/// Paging state of the log of a pool. Pages go from the newest pull to the oldest, so the
+/// pulls here are newest first and `end_id` points to the oldest of them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// key of the pool
+    pub key: String,
+    /// uid of the account, 0 if nothing is fetched yet
+    pub uid: usize,
+    /// cursor of the next page, 0 for the first page
+    pub end_id: u64,
+    /// pulls fetched so far, newest first
+    pub pulls: Vec<Pull>,
+    /// ids of the records skipped so far as they cannot be understood
+    #[serde(default)]
+    pub skipped: Vec<u64>,
+}
+
+impl Checkpoint {
+    /// A checkpoint of pool identified by `key` with nothing fetched
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_owned(),
+            uid: 0,
+            end_id: 0,
+            pulls: vec![],
+            skipped: vec![],
+        }
+    }
+
+    /// Path of the checkpoint of pool identified by `key` under `root`
+    fn path(root: &Path, key: &str) -> PathBuf {
+        root.join(format!("{}.json", key))
+    }
+
+    /// Load the checkpoint of pool identified by `key` under `root`, if any
+    pub fn load(root: &Path, key: &str) -> anyhow::Result<Option<Self>> {
+        let path = Self::path(root, key);
+        match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))
+                .map(|checkpoint: Self| {
+                    Some(Self {
+                        pulls: checkpoint
+                            .pulls
+                            .into_iter()
+                            .map(Pull::in_server_time)
+                            .collect(),
+                        ..checkpoint
+                    })
+                })
+                .with_context(|| format!("断点文件 {} 已损坏，请删除后重试", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("无法读取断点文件 {}", path.display())),
+        }
+    }
+
+    /// Save the checkpoint under `root`. The file is replaced as a whole so that an
+    /// interruption never leaves a half-written checkpoint
+    pub fn save(&self, root: &Path) -> anyhow::Result<()> {
+        create_dir_all(root).with_context(|| format!("无法创建断点目录 {}", root.display()))?;
+        let path = Self::path(root, &self.key);
+        let temp_path = path.with_extension("json.tmp");
+        let write = || -> anyhow::Result<()> {
+            let mut output = BufWriter::new(File::create(&temp_path)?);
+            serde_json::to_writer(&mut output, self)?;
+            output.flush()?;
+            output.get_ref().sync_all()?;
+            rename(&temp_path, &path)?;
+            Ok(())
+        };
+        write().with_context(|| format!("无法写入断点文件 {}", path.display()))
+    }
+
+    /// Remove the checkpoint of pool identified by `key` under `root` once the fetch is done
+    pub fn remove(root: &Path, key: &str) -> anyhow::Result<()> {
+        let path = Self::path(root, key);
+        match remove_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("无法删除断点文件 {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
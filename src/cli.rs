@@ -0,0 +1,586 @@
+/// Non-interactive command line interface
+use std::{
+    ffi::OsStr,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context};
+use console::style;
+use reqwest::Url;
+use structopt::StructOpt;
+
+use crate::{
+    cassette::CassetteMode,
+    checkpoint::DEFAULT_CHECKPOINT_DIR,
+    client::{Client, ClientConfig, ProxyConfig},
+    data_type::{interleave, Pool, PoolGroup, Pull},
+    database::{Database, DEFAULT_DATABASE_DIR},
+    export::{export_csv, export_json, export_uigf, ISO_TIME_FORMAT},
+    import::{import_csv, import_json, import_uigf},
+    item_database::{self, ItemDatabase},
+    merge::{merge, Merged},
+    mitm::tap_for_url,
+    pool_catalogue,
+    report::{banner::Banners, guarantee::Guarantee, pity::Pity, summary::Summary, Report},
+};
+
+/// Command line options, the interactive wizard is used when no subcommand is given
+#[derive(Debug, StructOpt)]
+#[structopt(about = "原神抽卡记录导出工具")]
+pub struct Opt {
+    /// 每秒最多发送的请求数
+    #[structopt(long, default_value = "5", global = true, parse(try_from_str = parse_rate))]
+    pub requests_per_second: f64,
+    /// 请求失败时的最大重试次数
+    #[structopt(long, default_value = "5", global = true)]
+    pub max_retries: u32,
+    /// 断点目录，获取中断时已获取的记录保存在此，下次获取时从断点继续
+    #[structopt(long, default_value = DEFAULT_CHECKPOINT_DIR, global = true, parse(from_os_str))]
+    pub checkpoint: PathBuf,
+    /// 不使用断点
+    #[structopt(long, global = true)]
+    pub no_checkpoint: bool,
+    /// API的基础网址，不指定时根据抽卡记录网址决定
+    #[structopt(long, global = true)]
+    pub api_base_url: Option<String>,
+    /// 将所有请求及响应录制到文件，其中的authkey会被隐去，用于反馈问题
+    #[structopt(long, global = true, parse(from_os_str), conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// 从录制的文件回放请求，不访问网络
+    #[structopt(long, global = true, parse(from_os_str))]
+    pub replay: Option<PathBuf>,
+    /// 访问服务器使用的代理，如http://127.0.0.1:8080或socks5://127.0.0.1:1080；为env时使用环境变量HTTPS_PROXY或ALL_PROXY
+    #[structopt(long, global = true, parse(from_str = parse_proxy))]
+    pub proxy: Option<ProxyConfig>,
+    /// 额外信任的根证书，PEM或DER格式，可以指定多次
+    #[structopt(long, global = true, parse(from_os_str), number_of_values = 1)]
+    pub root_certificate: Vec<PathBuf>,
+    /// 无法获取卡池列表时逐个检查的卡池，JSON格式，不指定时使用内置列表
+    #[structopt(long, global = true, parse(from_os_str))]
+    pub pool_catalogue: Option<PathBuf>,
+    /// 物品数据库，JSON格式，其中的物品替换内置数据库中ID相同的物品
+    #[structopt(long, global = true, parse(from_os_str))]
+    pub item_database: Option<PathBuf>,
+    /// 跳过无法解析的抽卡记录并输出警告，而不是中止获取
+    #[structopt(long, global = true)]
+    pub skip_bad_records: bool,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Opt {
+    /// Configuration of the API client
+    pub fn client_config(&self) -> ClientConfig {
+        ClientConfig {
+            requests_per_second: self.requests_per_second,
+            max_retries: self.max_retries,
+            checkpoint_dir: if self.no_checkpoint {
+                None
+            } else {
+                Some(self.checkpoint.clone())
+            },
+            api_base_url: self.api_base_url.clone(),
+            cassette: match (&self.record, &self.replay) {
+                (Some(path), _) => Some(CassetteMode::Record(path.clone())),
+                (None, Some(path)) => Some(CassetteMode::Replay(path.clone())),
+                (None, None) => None,
+            },
+            proxy: self.proxy.clone().unwrap_or_default(),
+            root_certificates: self.root_certificate.clone(),
+            pool_catalogue: self.pool_catalogue.clone(),
+            skip_bad_records: self.skip_bad_records,
+        }
+    }
+
+    /// Use the item database given, if any, instead of the bundled one
+    pub fn install_item_database(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.item_database {
+            item_database::install(ItemDatabase::load(path)?);
+        }
+        Ok(())
+    }
+}
+
+// Subcommands for scripted usage, doc comments of variants are used as help messages
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// 启动HTTP代理获取抽卡记录网址，并输出到标准输出
+    Capture,
+    /// 获取抽卡记录，并以制表符分隔的格式输出到标准输出
+    Fetch {
+        #[structopt(flatten)]
+        source: Source,
+    },
+    /// 增量同步抽卡记录到本地数据库
+    Sync {
+        /// 抽卡记录网址
+        #[structopt(long, parse(try_from_str = parse_url))]
+        url: Url,
+        /// 卡池，可以是卡池的ID、编号（如301）或名称，共享保底的角色活动祈愿一起同步；不指定时同步所有卡池
+        #[structopt(long)]
+        pool: Option<String>,
+        /// 本地数据库目录
+        #[structopt(long, default_value = DEFAULT_DATABASE_DIR, parse(from_os_str))]
+        database: PathBuf,
+    },
+    /// 获取抽卡记录并导出到文件
+    Export {
+        #[structopt(flatten)]
+        source: Source,
+        /// 导出格式
+        #[structopt(long, default_value = "csv", possible_values = ExportFormat::VARIANTS)]
+        format: ExportFormat,
+        /// 保存位置
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// 获取抽卡记录并输出统计报告
+    Report {
+        #[structopt(flatten)]
+        source: Source,
+        /// 报告类型，summary为综合统计，pity为每个四星及五星的抽数，guarantee为活动祈愿的保底情况，banner为每期活动祈愿的抽数及UP率
+        #[structopt(long, default_value = "summary", possible_values = ReportKind::VARIANTS)]
+        kind: ReportKind,
+        /// 保存位置，不指定时输出到标准输出
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+// Where the gacha log comes from
+#[derive(Debug, StructOpt)]
+pub struct Source {
+    /// 抽卡记录网址，不指定时只读取本地数据库
+    #[structopt(
+        long,
+        parse(try_from_str = parse_url),
+        required_unless_one = &["database", "input"]
+    )]
+    url: Option<Url>,
+    /// 卡池，可以是卡池的ID、编号（如301）或名称，共享保底的角色活动祈愿视为一个卡池；只导入文件时须为编号，不指定时使用全部记录
+    #[structopt(long, required_unless_one = &["input", "all-pools"])]
+    pool: Option<String>,
+    /// 同时获取所有卡池的记录，合并为一份
+    #[structopt(long, conflicts_with = "pool")]
+    all_pools: bool,
+    /// 本地数据库目录，指定时先增量同步抽卡记录，再使用数据库中的完整记录
+    #[structopt(long, parse(from_os_str))]
+    database: Option<PathBuf>,
+    /// 从文件导入抽卡记录，可以指定多次；与其它来源一起指定时合并所有记录并去重
+    #[structopt(long, parse(from_os_str), number_of_values = 1)]
+    input: Vec<PathBuf>,
+    /// 导入文件的格式，不指定时根据扩展名及内容判断
+    #[structopt(long, possible_values = ImportFormat::VARIANTS)]
+    input_format: Option<ImportFormat>,
+}
+
+/// Supported export formats
+#[derive(Debug, Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Uigf,
+}
+
+impl ExportFormat {
+    const VARIANTS: &'static [&'static str] = &["csv", "json", "uigf"];
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "uigf" => Ok(Self::Uigf),
+            _ => Err(anyhow!("不支持的导出格式: {}", s)),
+        }
+    }
+}
+
+/// Supported reports
+#[derive(Debug, Clone, Copy)]
+pub enum ReportKind {
+    Summary,
+    Pity,
+    Guarantee,
+    Banner,
+}
+
+impl ReportKind {
+    const VARIANTS: &'static [&'static str] = &["summary", "pity", "guarantee", "banner"];
+}
+
+impl FromStr for ReportKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "summary" => Ok(Self::Summary),
+            "pity" => Ok(Self::Pity),
+            "guarantee" => Ok(Self::Guarantee),
+            "banner" => Ok(Self::Banner),
+            _ => Err(anyhow!("不支持的报告类型: {}", s)),
+        }
+    }
+}
+
+/// Supported import formats
+#[derive(Debug, Clone, Copy)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+    Uigf,
+}
+
+impl ImportFormat {
+    const VARIANTS: &'static [&'static str] = &["csv", "json", "uigf"];
+
+    /// Guess the format of a file from its extension, json files written by this tool are
+    /// told from UIGF ones by their content
+    fn guess(path: &Path) -> Self {
+        match path.extension().and_then(OsStr::to_str) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Self::Csv,
+            _ if is_json_export(path) => Self::Json,
+            _ => Self::Uigf,
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "uigf" => Ok(Self::Uigf),
+            _ => Err(anyhow!("不支持的导入格式: {}", s)),
+        }
+    }
+}
+
+/// Whether `path` is a json file written by [`export_json`], which has a list of pulls at
+/// the top level
+fn is_json_export(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+        .map_or(false, |document: serde_json::Value| {
+            document
+                .get("pulls")
+                .map_or(false, serde_json::Value::is_array)
+        })
+}
+
+/// Parse an url and make sure it is something from in-game client
+fn parse_url(input: &str) -> anyhow::Result<Url> {
+    let url = Url::parse(input).map_err(|err| anyhow!("输入不是网址: {}", err))?;
+    if Client::verify_url(&url) {
+        Ok(url)
+    } else {
+        Err(anyhow!("输入网址不是有效的抽卡记录网址"))
+    }
+}
+
+/// Parse the proxy option, `env` stands for the proxy in environment variables
+fn parse_proxy(input: &str) -> ProxyConfig {
+    if input == "env" {
+        ProxyConfig::Env
+    } else {
+        ProxyConfig::Url(input.to_owned())
+    }
+}
+
+/// Parse a request rate, which must be positive
+fn parse_rate(input: &str) -> anyhow::Result<f64> {
+    let rate: f64 = input.parse()?;
+    if rate > 0.0 && rate.is_finite() {
+        Ok(rate)
+    } else {
+        Err(anyhow!("请求速率必须为正数"))
+    }
+}
+
+/// Find a pool by its id, key or name
+fn find_pool<'a>(pools: &'a [Pool], needle: &str) -> Option<&'a Pool> {
+    pools
+        .iter()
+        .find(|pool| pool.id.to_string() == needle || pool.key == needle || pool.name == needle)
+}
+
+/// Find the group of `pools` that a pool identified by `needle` is in, the name of the
+/// group is accepted as well. Character event wishes share pity and come as one group
+fn find_group(pools: &[Pool], needle: &str) -> Option<PoolGroup> {
+    PoolGroup::group(pools)
+        .into_iter()
+        .find(|group| group.name == needle || find_pool(&group.pools, needle).is_some())
+}
+
+impl Source {
+    /// Fetch the gacha log described by the source, along with the pools it comes from.
+    /// Logs from more than one source are merged
+    async fn fetch(&self, config: &ClientConfig) -> anyhow::Result<(Vec<Pool>, Vec<Pull>)> {
+        let mut pools = vec![];
+        let mut logs = vec![];
+        if self.url.is_some() || self.database.is_some() {
+            let (remote_pools, log) = self.fetch_remote(config).await?;
+            pools = remote_pools;
+            logs.push(log);
+        }
+        // only pulls from the same pools are comparable
+        let keys: Option<Vec<String>> = match &self.pool {
+            _ if self.all_pools => None,
+            _ if !pools.is_empty() => Some(pools.iter().map(|pool| pool.key.clone()).collect()),
+            Some(needle) => {
+                let known: Vec<Pool> = pool_catalogue::builtin()
+                    .iter()
+                    .map(|entry| entry.to_pool("zh-cn"))
+                    .collect();
+                Some(find_group(&known, needle).map_or_else(
+                    || vec![needle.clone()],
+                    |group| group.pools.into_iter().map(|pool| pool.key).collect(),
+                ))
+            }
+            None => None,
+        };
+        for input in self.input.iter() {
+            logs.push(self.import(input, keys.as_deref())?);
+        }
+        if logs.len() == 1 {
+            return Ok((pools, logs.pop().unwrap()));
+        }
+        let merged = merge(logs);
+        print_merge_report(&merged);
+        Ok((pools, merged.pulls))
+    }
+
+    /// Import the gacha log from a file, only keeping pulls from pools identified by `keys`
+    fn import(&self, input: &Path, keys: Option<&[String]>) -> anyhow::Result<Vec<Pull>> {
+        let format = self
+            .input_format
+            .unwrap_or_else(|| ImportFormat::guess(input));
+        let mut log = match format {
+            ImportFormat::Csv => import_csv(input)?,
+            ImportFormat::Json => import_json(input)?,
+            ImportFormat::Uigf => import_uigf(input)?,
+        };
+        // csv files written by older versions do not record the pool, their pulls are taken
+        // as from the specified pool so that reports per pool see them
+        let has_legacy = log.iter().any(|pull| pull.gacha_type.is_empty());
+        match keys {
+            Some(keys) => {
+                for pull in log.iter_mut().filter(|pull| pull.gacha_type.is_empty()) {
+                    pull.gacha_type = keys[0].clone();
+                }
+                log.retain(|pull| keys.contains(&pull.gacha_type));
+            }
+            None if has_legacy => {
+                return Err(anyhow!(
+                    "{} 由旧版本导出，没有记录卡池，须使用--pool指定卡池编号",
+                    input.display()
+                ))
+            }
+            None => (),
+        }
+        Ok(log)
+    }
+
+    /// Fetch the gacha log from the API or the local database, along with the pools it
+    /// comes from
+    async fn fetch_remote(&self, config: &ClientConfig) -> anyhow::Result<(Vec<Pool>, Vec<Pull>)> {
+        if self.all_pools {
+            return self.fetch_all_remote(config).await;
+        }
+        let needle = self.pool.as_deref().ok_or_else(|| anyhow!("未指定卡池"))?;
+        let url = match (&self.url, &self.database) {
+            (Some(url), _) => url,
+            (None, Some(database)) => {
+                let database = Database::open(database)?;
+                let group = find_group(&database.load_pools()?, needle)
+                    .ok_or_else(|| anyhow!("数据库中没有卡池: {}", needle))?;
+                let log = database.load_group(&group)?;
+                return Ok((group.pools, log));
+            }
+            (None, None) => return Err(anyhow!("未指定网址或本地数据库")),
+        };
+        let client = Client::new(url.clone(), config)
+            .await
+            .context("初始化客户端失败")?;
+        let group = find_group(client.get_pools(), needle)
+            .ok_or_else(|| anyhow!("找不到卡池: {}", needle))?;
+        let log = match &self.database {
+            Some(database) => Database::open(database)?
+                .sync_group(&client, &group)
+                .await
+                .context("同步抽卡记录失败")?,
+            None => interleave(
+                client
+                    .request_gacha_logs_since(
+                        &group
+                            .pools
+                            .iter()
+                            .map(|pool| (pool, None))
+                            .collect::<Vec<_>>(),
+                    )
+                    .await
+                    .context("获取抽卡记录失败")?,
+            ),
+        };
+        Ok((group.pools, log))
+    }
+
+    /// Fetch the gacha log of all the pools from the API or the local database, pulls of
+    /// different pools are interleaved by time
+    async fn fetch_all_remote(
+        &self,
+        config: &ClientConfig,
+    ) -> anyhow::Result<(Vec<Pool>, Vec<Pull>)> {
+        let (pools, logs) = match (&self.url, &self.database) {
+            (Some(url), database) => {
+                let client = Client::new(url.clone(), config)
+                    .await
+                    .context("初始化客户端失败")?;
+                let pools = client.get_pools().clone();
+                let logs = match database {
+                    Some(database) => Database::open(database)?
+                        .sync_all(&client, &pools)
+                        .await
+                        .context("同步抽卡记录失败")?,
+                    None => client
+                        .request_gacha_logs_since(
+                            &pools.iter().map(|pool| (pool, None)).collect::<Vec<_>>(),
+                        )
+                        .await
+                        .context("获取抽卡记录失败")?,
+                };
+                (pools, logs)
+            }
+            (None, Some(database)) => {
+                let database = Database::open(database)?;
+                let pools = database.load_pools()?;
+                let logs = pools
+                    .iter()
+                    .map(|pool| database.load(&pool.key))
+                    .collect::<anyhow::Result<_>>()?;
+                (pools, logs)
+            }
+            (None, None) => return Err(anyhow!("未指定网址或本地数据库")),
+        };
+        Ok((pools, interleave(logs)))
+    }
+}
+
+/// Tell the user how the logs are merged
+fn print_merge_report(merged: &Merged) {
+    eprintln!(
+        "合并后共{}条记录，其中{}条重复记录已去除",
+        merged.pulls.len(),
+        merged.overlaps
+    );
+    for conflict in merged.conflicts.iter() {
+        eprintln!(
+            "{} 冲突的记录: 保留 {} {}，舍弃 {} {}",
+            style("[警告]").red(),
+            conflict.kept.time.format("%Y-%m-%d %T"),
+            conflict.kept.item.name,
+            conflict.dropped.time.format("%Y-%m-%d %T"),
+            conflict.dropped.item.name,
+        );
+    }
+}
+
+/// Run a subcommand
+pub async fn run(command: Command, config: &ClientConfig) -> anyhow::Result<()> {
+    match command {
+        Command::Capture => {
+            let url = tap_for_url().await?;
+            println!("{}", url);
+        }
+        Command::Fetch { source } => {
+            let (_, log) = source.fetch(config).await?;
+            for pull in log.iter() {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    pull.time.format(ISO_TIME_FORMAT),
+                    pull.item.name,
+                    pull.item.item_type,
+                    pull.item.rarity,
+                    pull.id.map(|id| id.to_string()).unwrap_or_default(),
+                    pull.uid,
+                    pull.gacha_type,
+                    pull.count,
+                    pull.lang,
+                    pull.item.item_id,
+                );
+            }
+        }
+        Command::Sync {
+            url,
+            pool,
+            database,
+        } => {
+            let database = Database::open(database)?;
+            let client = Client::new(url, config).await.context("初始化客户端失败")?;
+            match pool {
+                Some(pool) => {
+                    let group = find_group(client.get_pools(), &pool)
+                        .ok_or_else(|| anyhow!("找不到卡池: {}", pool))?;
+                    database
+                        .sync_group(&client, &group)
+                        .await
+                        .with_context(|| format!("同步{}失败", group))?;
+                }
+                None => {
+                    database
+                        .sync_all(&client, client.get_pools())
+                        .await
+                        .context("同步抽卡记录失败")?;
+                }
+            }
+        }
+        Command::Export {
+            source,
+            format,
+            output,
+        } => {
+            let (pools, log) = source.fetch(config).await?;
+            match format {
+                ExportFormat::Csv => export_csv(&log, &output),
+                ExportFormat::Json => export_json(&pools, &log, &output),
+                ExportFormat::Uigf => export_uigf(&log, &output),
+            }
+            .context("保存文件失败")?;
+        }
+        Command::Report {
+            source,
+            kind,
+            output,
+        } => {
+            let (_, log) = source.fetch(config).await?;
+            match kind {
+                ReportKind::Summary => output_report(&Summary::new(&log), output)?,
+                ReportKind::Pity => output_report(&Pity::new(&log), output)?,
+                ReportKind::Guarantee => output_report(&Guarantee::new(&log), output)?,
+                ReportKind::Banner => output_report(&Banners::new(&log), output)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the report to `output`, or print it if no file is specified
+fn output_report<R: Report>(report: &R, output: Option<PathBuf>) -> anyhow::Result<()> {
+    match output {
+        Some(path) => {
+            let mut file = File::create(&path).context("保存文件失败")?;
+            report.write(&mut file).context("保存文件失败")?;
+        }
+        None => report.print(),
+    }
+    Ok(())
+}
@@ -1,38 +1,48 @@
 /// Client for Genshin API
-use std::{cell::Cell, collections::HashMap, future, iter::once, rc::Rc};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    env,
+    error::Error,
+    fmt, fs,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, Once,
+    },
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context};
-use chrono::{Local, TimeZone};
-use futures::stream::{self, StreamExt, TryStreamExt};
+use chrono::TimeZone;
+use console::style;
+use futures::future;
 use indicatif::{MultiProgress, ProgressBar};
+use rand::{thread_rng, Rng};
 use reqwest::{
     header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE, UPGRADE_INSECURE_REQUESTS},
-    Client as ReqClient, Url,
+    Certificate, Client as ReqClient, Proxy, StatusCode, Url,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
-use tokio::task::spawn_blocking;
+use tokio::{
+    signal::ctrl_c,
+    task::spawn_blocking,
+    time::{sleep, sleep_until, Instant},
+};
 
 use crate::{
-    data_type::{Item, ItemType, Pool, Pull, Rarity},
+    cassette::{Cassette, CassetteMode},
+    checkpoint::{Checkpoint, DEFAULT_CHECKPOINT_DIR},
+    data_type::{region_offset, Item, ItemType, Pool, Pull, Rarity},
     mitm::PAGE_INTERCEPT_SUFFIX,
+    pool_catalogue::{self, CatalogueEntry},
     style::SPINNER_STYLE,
 };
 
-/// Return the url for item list given region of server and language to use
-fn item_list_url(region: &str, lang: &str) -> Url {
-    Url::parse(&format!(
-        "https://webstatic-sea.mihoyo.com/hk4e/gacha_info/{}/items/{}.json",
-        region, lang
-    ))
-    .unwrap()
-}
-
-/// ID for "The Stringless", used to identify the local identifier for weapon
-const WEAPON_ID: &str = "15405";
-/// ID for "Venti", used to identify the local identifier for character
-const CHARACTER_ID: &str = "1022";
-
 /// The user-agent to use
 const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 6.1; Unity 3D; ZFBrowser 2.1.0; Genshin Impact 1.2.0_1565149_1627898) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/72.0.3626.96 Safari/537.36";
 
@@ -44,6 +54,118 @@ struct ApiResponse<T> {
     data: Option<T>,
 }
 
+impl<T> ApiResponse<T> {
+    /// Extract the payload, turning known retcodes into [`ApiError`]
+    fn into_result(self) -> Result<T, ApiError> {
+        match (self.retcode, self.data) {
+            (0, Some(data)) => Ok(data),
+            (0, None) => Err(ApiError::MissingData),
+            (-100, _) => Err(ApiError::InvalidAuthkey),
+            (-101, _) => Err(ApiError::AuthkeyTimeout),
+            (-110, _) => Err(ApiError::VisitTooFrequently),
+            (retcode, _) => Err(ApiError::Unknown {
+                retcode,
+                message: self.message,
+            }),
+        }
+    }
+}
+
+/// Errors reported by Genshin API server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// retcode -100, the authkey is malformed or from a different url
+    InvalidAuthkey,
+    /// retcode -101, the authkey is only valid for a day
+    AuthkeyTimeout,
+    /// retcode -110, requests are sent too fast
+    VisitTooFrequently,
+    /// retcode 0 but no payload
+    MissingData,
+    /// any other retcode
+    Unknown { retcode: i32, message: String },
+}
+
+impl ApiError {
+    /// What goes wrong, for the user
+    pub fn explanation(&self) -> String {
+        match self {
+            Self::InvalidAuthkey => "网址中的authkey无效".to_owned(),
+            Self::AuthkeyTimeout => "网址中的authkey已过期".to_owned(),
+            Self::VisitTooFrequently => "访问过于频繁".to_owned(),
+            Self::MissingData => "服务器没有返回数据".to_owned(),
+            Self::Unknown { retcode, message } => {
+                format!("服务器返回错误{}: {}", retcode, message)
+            }
+        }
+    }
+
+    /// How the user may recover from the error
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::InvalidAuthkey => {
+                "请确认网址完整，或在游戏中重新打开祈愿历史记录页面并重新获取网址"
+            }
+            Self::AuthkeyTimeout => "请在游戏中重新打开祈愿历史记录页面并重新获取网址",
+            Self::VisitTooFrequently => "请稍等片刻后重试",
+            Self::MissingData | Self::Unknown { .. } => "请稍后重试，若问题持续出现请重新获取网址",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}，{}", self.explanation(), self.hint())
+    }
+}
+
+impl Error for ApiError {}
+
+/// Pulls fetched from a pool
+#[derive(Debug, Default)]
+pub struct FetchedLog {
+    /// chronological pulls
+    pub pulls: Vec<Pull>,
+    /// ids of the records skipped as they cannot be understood, see
+    /// [`ClientConfig::skip_bad_records`]
+    pub skipped: Vec<u64>,
+}
+
+/// A record of gacha log that cannot be understood, usually from a newer version of the game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    pub kind: RecordErrorKind,
+    /// the record as received, in JSON
+    pub record: String,
+}
+
+/// What is wrong with a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordErrorKind {
+    /// `rank_type` other than 3, 4 and 5
+    Rarity(String),
+    /// `time` not in the format of `2021-03-01 12:00:00`
+    Time(String),
+    /// `item_type` unknown and `item_id` out of the known ranges
+    ItemType(String),
+    /// `count` not a number
+    Count(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match &self.kind {
+            RecordErrorKind::Rarity(rank_type) => format!("未知的稀有度: {}", rank_type),
+            RecordErrorKind::Time(time) => format!("无效的时间: {}", time),
+            RecordErrorKind::ItemType(item_type) => format!("无法识别的类型: {}", item_type),
+            RecordErrorKind::Count(count) => format!("无效的数量: {}", count),
+        };
+        write!(f, "无法解析抽卡记录，{}，记录为 {}", problem, self.record)
+    }
+}
+
+impl Error for RecordError {}
+
 /// Information of a pool
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,12 +176,12 @@ struct GachaConfig {
     name: String,
 }
 
-impl Into<Pool> for GachaConfig {
-    fn into(self) -> Pool {
-        Pool {
-            id: self.id,
-            key: self.key,
-            name: self.name,
+impl From<GachaConfig> for Pool {
+    fn from(config: GachaConfig) -> Self {
+        Self {
+            id: config.id,
+            key: config.key,
+            name: config.name,
         }
     }
 }
@@ -98,77 +220,385 @@ struct GachaResultPage {
     region: String,
 }
 
-/// Payload for [`item_list_url`]
-#[serde_as]
+/// Item of a pull
 #[derive(Debug, Serialize, Deserialize)]
 struct GachaItem {
     item_id: String,
     name: String,
     item_type: String,
-    #[serde_as(as = "DisplayFromStr")]
-    rank_type: u8,
+    rank_type: String,
+}
+
+/// How the client talks to the API server
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// maximum number of requests sent per second
+    pub requests_per_second: f64,
+    /// maximum number of retries of a failed page request
+    pub max_retries: u32,
+    /// where the progress of unfinished fetches is kept, `None` to disable resuming
+    pub checkpoint_dir: Option<PathBuf>,
+    /// base url of the API such as `https://hk4e-api.mihoyo.com/event/gacha_info/api`,
+    /// `None` to derive it from the url of gacha page
+    pub api_base_url: Option<String>,
+    /// record the traffic to or replay it from a cassette
+    pub cassette: Option<CassetteMode>,
+    /// outgoing proxy
+    pub proxy: ProxyConfig,
+    /// files of extra trusted root certificates
+    pub root_certificates: Vec<PathBuf>,
+    /// pools to probe when the API does not list them, `None` for the built-in ones
+    pub pool_catalogue: Option<PathBuf>,
+    /// skip records that cannot be understood with a warning, instead of failing the fetch
+    pub skip_bad_records: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 5.0,
+            max_retries: 5,
+            checkpoint_dir: Some(PathBuf::from(DEFAULT_CHECKPOINT_DIR)),
+            api_base_url: None,
+            cassette: None,
+            proxy: ProxyConfig::None,
+            root_certificates: vec![],
+            pool_catalogue: None,
+            skip_bad_records: false,
+        }
+    }
+}
+
+/// Delay before the first retry, doubled on every following retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound of the delay between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(16);
+
+/// Spaces out requests so that they are sent no faster than the configured rate, shared by
+/// all the concurrent requests of a client
+#[derive(Debug)]
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / requests_per_second),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until the next request is allowed
+    async fn wait(&self) {
+        let deadline = {
+            let mut next = self.next.lock().unwrap();
+            let deadline = (*next).max(Instant::now());
+            *next = deadline + self.interval;
+            deadline
+        };
+        sleep_until(deadline).await;
+    }
+}
+
+/// Whether a failed request is rejected because the endpoint does not exist
+fn is_not_found(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<HttpStatusError>(),
+        Some(HttpStatusError(StatusCode::NOT_FOUND))
+    )
+}
+
+/// Number of fetches running, Ctrl-C interrupts them instead of the process
+static RUNNING_FETCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Makes sure Ctrl-C still terminates the process when no fetch is running
+static INTERRUPT_HANDLER: Once = Once::new();
+
+/// Marks a fetch as running for as long as it lives. Listening to Ctrl-C turns off the
+/// default handling for the rest of the process, so on the first fetch a task takes over
+/// to terminate the process on Ctrl-C outside of fetches, such as at later prompts
+struct InterruptibleFetch;
+
+impl InterruptibleFetch {
+    fn start() -> Self {
+        INTERRUPT_HANDLER.call_once(|| {
+            tokio::spawn(async {
+                while ctrl_c().await.is_ok() {
+                    if RUNNING_FETCHES.load(Ordering::SeqCst) == 0 {
+                        process::exit(130);
+                    }
+                }
+            });
+        });
+        RUNNING_FETCHES.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for InterruptibleFetch {
+    fn drop(&mut self) {
+        RUNNING_FETCHES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether a failed request is worth retrying
+fn is_transient(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.downcast_ref::<ApiError>() {
+        return *err == ApiError::VisitTooFrequently;
+    }
+    if let Some(HttpStatusError(status)) = err.downcast_ref::<HttpStatusError>() {
+        return status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS;
+    }
+    err.downcast_ref::<reqwest::Error>().map_or(false, |err| {
+        err.is_timeout() || err.is_connect() || err.is_request()
+    })
+}
+
+/// A response with unsuccessful HTTP status
+#[derive(Debug)]
+pub struct HttpStatusError(pub StatusCode);
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "服务器返回HTTP错误{}", self.0)
+    }
+}
+
+impl Error for HttpStatusError {}
+
+/// A response received by a [`Transport`]
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+/// Future returned by [`Transport::get`]
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = anyhow::Result<HttpResponse>> + Send + 'a>>;
+
+/// How requests of [`Client`] reach the server, both the API and the item lists go through it
+pub trait Transport: fmt::Debug + Send + Sync {
+    /// Send a GET request to `url`
+    fn get<'a>(&'a self, url: &'a Url) -> TransportFuture<'a>;
+}
+
+/// Environment variables holding the outgoing proxy, in order of precedence
+const PROXY_ENV_VARS: &[&str] = &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// How requests reach the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyConfig {
+    /// connect directly, the system proxy is ignored as it may be our own capturing proxy
+    None,
+    /// use the proxy in environment variable `HTTPS_PROXY` or `ALL_PROXY`
+    Env,
+    /// use the proxy at the url, schemes `http`, `https`, `socks5` and `socks5h` are supported
+    Url(String),
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// The default transport backed by reqwest, disguised as the in-game browser
+#[derive(Debug)]
+pub struct ReqwestTransport {
+    client: ReqClient,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::with_config(&ProxyConfig::None, &[]).unwrap()
+    }
+
+    /// Create the transport going through `proxy` and trusting `root_certificates` on top
+    /// of the system ones. Each certificate file is either PEM, possibly holding several
+    /// certificates, or DER
+    pub fn with_config(proxy: &ProxyConfig, root_certificates: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
+        headers.insert(UPGRADE_INSECURE_REQUESTS, HeaderValue::from_static("1"));
+        let mut builder = ReqClient::builder()
+            .default_headers(headers)
+            .user_agent(USER_AGENT)
+            .gzip(true)
+            .no_proxy();
+        let proxy_url = match proxy {
+            ProxyConfig::None => None,
+            ProxyConfig::Env => Some(
+                PROXY_ENV_VARS
+                    .iter()
+                    .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+                    .ok_or_else(|| anyhow!("环境变量{}均未设置代理", PROXY_ENV_VARS.join("、")))?,
+            ),
+            ProxyConfig::Url(url) => Some(url.clone()),
+        };
+        if let Some(url) = proxy_url {
+            builder =
+                builder.proxy(Proxy::all(&url).with_context(|| format!("代理网址无效: {}", url))?);
+        }
+        for path in root_certificates.iter() {
+            for certificate in load_certificates(path)
+                .with_context(|| format!("无法加载根证书 {}", path.display()))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(Self {
+            client: builder.build().context("无法建立网络客户端")?,
+        })
+    }
+}
+
+/// Load certificates from a PEM or DER file
+fn load_certificates(path: &Path) -> anyhow::Result<Vec<Certificate>> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+    let content = fs::read(path)?;
+    let text = match std::str::from_utf8(&content) {
+        Ok(text) if text.contains(BEGIN) => text,
+        _ => return Ok(vec![Certificate::from_der(&content)?]),
+    };
+    // native TLS only takes the first certificate of a PEM
+    text.match_indices(BEGIN)
+        .map(|(start, _)| {
+            let end = text[start..]
+                .find(END)
+                .map(|end| start + end + END.len())
+                .ok_or_else(|| anyhow!("PEM证书不完整"))?;
+            Ok(Certificate::from_pem(&text.as_bytes()[start..end])?)
+        })
+        .collect()
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn get<'a>(&'a self, url: &'a Url) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let resp = self.client.get(url.clone()).send().await?;
+            let status = resp.status();
+            let body = resp.text().await?;
+            Ok(HttpResponse { status, body })
+        })
+    }
+}
+
+/// Get `url` through `transport` and parse the response as JSON
+async fn get_json<T>(transport: &dyn Transport, url: Url) -> anyhow::Result<T>
+where
+    for<'a> T: Deserialize<'a>,
+{
+    let resp = transport.get(&url).await?;
+    if !resp.status.is_success() {
+        return Err(HttpStatusError(resp.status).into());
+    }
+    Ok(serde_json::from_str(&resp.body)?)
+}
+
+/// Delay before retry number `attempt` (starting from 0), exponential with jitter so that
+/// concurrent requests do not retry in lockstep
+fn retry_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY
+        .checked_mul(1 << attempt.min(16))
+        .unwrap_or(RETRY_MAX_DELAY)
+        .min(RETRY_MAX_DELAY);
+    delay / 2 + delay.mul_f64(thread_rng().gen_range(0.0..0.5))
 }
 
 /// A client used to query Genshin gacha info
 #[derive(Debug)]
 pub struct Client {
-    /// identifier for a weapon
-    weapon_identifier: String,
-    /// identifier for a character
-    character_identifier: String,
     /// metadata for pools
     pools: Vec<Pool>,
-    /// backing http client
-    client: ReqClient,
+    /// backing http transport
+    transport: Box<dyn Transport>,
     /// base query to use
     base_query: BaseQuery,
     /// base url to use
     base_url: String,
+    /// maximum number of retries of a failed page request
+    max_retries: u32,
+    /// limit of request rate
+    rate_limiter: RateLimiter,
+    /// where the progress of unfinished fetches is kept
+    checkpoint_dir: Option<PathBuf>,
+    /// whether records that cannot be understood are skipped
+    skip_bad_records: bool,
 }
 
 impl Client {
     /// Create the client from an url pointing to in-game gacha page
-    pub async fn new(url: Url) -> anyhow::Result<Self> {
-        let base_query = BaseQuery::new(&url)?;
+    pub async fn new(url: Url, config: &ClientConfig) -> anyhow::Result<Self> {
+        let transport = ReqwestTransport::with_config(&config.proxy, &config.root_certificates)?;
+        Self::with_transport(url, config, Box::new(transport)).await
+    }
 
-        let base_url = format!(
-            "{}://{}{}",
-            url.scheme(),
-            url.host_str().unwrap(),
-            url.path().replacen(PAGE_INTERCEPT_SUFFIX, "", 1)
-        );
+    /// Create the client sending requests through `transport`
+    pub async fn with_transport(
+        url: Url,
+        config: &ClientConfig,
+        transport: Box<dyn Transport>,
+    ) -> anyhow::Result<Self> {
+        let base_query = BaseQuery::new(&url)?;
 
-        // build web client
-        let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
-        headers.insert(UPGRADE_INSECURE_REQUESTS, HeaderValue::from_static("1"));
-        let client = ReqClient::builder()
-            .default_headers(headers)
-            .user_agent(USER_AGENT)
-            .gzip(true)
-            .no_proxy()
-            .build()
-            .unwrap();
+        let base_url = match &config.api_base_url {
+            Some(base_url) => base_url.trim_end_matches('/').to_owned(),
+            None => format!(
+                "{}://{}{}{}",
+                url.scheme(),
+                url.host_str().context("抽卡记录网址中缺少域名")?,
+                url.port()
+                    .map(|port| format!(":{}", port))
+                    .unwrap_or_default(),
+                url.path().replacen(PAGE_INTERCEPT_SUFFIX, "", 1)
+            ),
+        };
 
-        // acquire information of pools and items
-        let mp = MultiProgress::new();
-        let pools_pb = Self::add_spinner(&mp, 1, 2);
-        let items_pb = Self::add_spinner(&mp, 2, 2);
+        // record or replay the traffic if asked to
+        let transport = match &config.cassette {
+            Some(mode) => Box::new(Cassette::open(mode, transport)?),
+            None => transport,
+        };
 
-        let pools_task = Self::request_pools(&client, &base_query, &base_url, pools_pb);
-        let items_task = Self::request_items(&client, &base_query, items_pb);
-        let progress_task = spawn_blocking(move || mp.join());
-        let (pools, identifiers, _) = tokio::join!(pools_task, items_task, progress_task);
-        let pools = pools.context("加载卡池列表失败")?;
-        let (weapon_identifier, character_identifier) = identifiers.context("加载图鉴失败")?;
+        // acquire information of pools
+        let pools_pb = ProgressBar::new_spinner()
+            .with_style(SPINNER_STYLE.clone().template("{spinner:.green} {msg}"));
+        pools_pb.enable_steady_tick(5);
+        let catalogue = match &config.pool_catalogue {
+            Some(path) => pool_catalogue::load(path)?,
+            None => pool_catalogue::builtin(),
+        };
+        let rate_limiter = RateLimiter::new(config.requests_per_second);
+        let pools = Self::request_pools(
+            &*transport,
+            &base_query,
+            &base_url,
+            &catalogue,
+            &rate_limiter,
+            pools_pb,
+        )
+        .await
+        .context("加载卡池列表失败")?;
 
         Ok(Self {
-            weapon_identifier,
-            character_identifier,
             pools,
-            client,
+            transport,
             base_query,
             base_url,
+            max_retries: config.max_retries,
+            rate_limiter,
+            checkpoint_dir: config.checkpoint_dir.clone(),
+            skip_bad_records: config.skip_bad_records,
         })
     }
 
@@ -179,92 +609,288 @@ impl Client {
 
     /// Get a chronological log of all the pulls from `pool`
     pub async fn request_gacha_log(&self, pool: &Pool) -> anyhow::Result<Vec<Pull>> {
-        // set up additional queries
-        let query = vec![
-            ("init_type".to_owned(), pool.key.clone()),
-            ("gacha_type".to_owned(), pool.key.clone()),
-            ("size".to_owned(), "20".to_owned()),
-        ];
+        self.request_gacha_log_since(pool, None).await
+    }
+
+    /// Get a chronological log of the pulls from `pool` that are newer than the pull
+    /// identified by `last_id`. Paging stops as soon as `last_id` is reached
+    pub async fn request_gacha_log_since(
+        &self,
+        pool: &Pool,
+        last_id: Option<u64>,
+    ) -> anyhow::Result<Vec<Pull>> {
         // set up a progress bar
         let pb = ProgressBar::new_spinner().with_style(
             SPINNER_STYLE
                 .clone()
                 .template("{spinner:.green} {msg}加载{pos}次抽卡记录"),
         );
-        pb.set_message("正在加载，已");
-        // iterate through pages
-        let mut pull_list: Vec<Pull> = stream::iter(1..)
-            .scan(Rc::new(Cell::new(0u64)), |end_id, page: usize| {
-                let query = query.clone();
-                let end_id = end_id.clone();
-                async move {
-                    // get records from current page
-                    let page: anyhow::Result<GachaResultPage> = Self::issue_api(
-                        &self.client,
-                        &self.base_query,
-                        &format!("{}/getGachaLog", self.base_url),
-                        query
-                            .into_iter()
-                            .chain(once(("page".to_owned(), page.to_string())))
-                            .chain(once(("end_id".to_owned(), end_id.get().to_string()))),
-                    )
-                    .await;
-                    let page = match page {
-                        Ok(page) => page,
-                        Err(e) => return Some(Err(e)),
-                    };
-                    // update end_id
-                    if let Some(pull) = page.list.last() {
-                        end_id.set(pull.id);
-                    }
-                    // convert each pull from API format to our format
-                    let page: Vec<Pull> = page
-                        .list
-                        .into_iter()
-                        .map(|pull| Pull {
-                            time: Local.datetime_from_str(&pull.time, "%Y-%m-%d %T").unwrap(),
-                            item: {
-                                let rarity = match pull.item.rank_type {
-                                    5 => Rarity::Five,
-                                    4 => Rarity::Four,
-                                    3 => Rarity::Three,
-                                    _ => unreachable!("图鉴中含有范围外的稀有度"),
-                                };
-                                let item_type = if pull.item.item_type == self.weapon_identifier {
-                                    ItemType::Weapon
-                                } else {
-                                    ItemType::Character
-                                };
-                                Item {
-                                    name: pull.item.name,
-                                    rarity,
-                                    item_type,
-                                }
-                            },
-                        })
-                        .collect();
-                    Some(Ok::<_, anyhow::Error>(page))
-                }
-            })
-            // stop when a page is empty, indicating end of log
-            .try_take_while(|page| future::ready(Ok(!page.is_empty())))
-            .and_then(|page| {
-                // update the progress bar
-                pb.inc(page.len() as u64);
-                future::ready(Ok(stream::iter(
-                    page.into_iter().map(Ok::<_, anyhow::Error>),
-                )))
+        Ok(self
+            .request_gacha_log_with_progress(pool, last_id, None, pb)
+            .await?
+            .pulls)
+    }
+
+    /// Get chronological logs of several pools concurrently, each paired with the id of the
+    /// last known pull of the pool. Every pool has its own spinner in one [`MultiProgress`]
+    pub async fn request_gacha_logs_since(
+        &self,
+        requests: &[(&Pool, Option<u64>)],
+    ) -> anyhow::Result<Vec<Vec<Pull>>> {
+        Ok(self
+            .request_account_logs_since(None, requests)
+            .await?
+            .into_iter()
+            .map(|log| log.pulls)
+            .collect())
+    }
+
+    /// Same as [`request_gacha_logs_since`] for the account `uid`, if it is known before
+    /// fetching. Checkpoints of a known account are kept apart from the others. The ids of
+    /// skipped records are returned along with the pulls
+    ///
+    /// [`request_gacha_logs_since`]: Self::request_gacha_logs_since
+    pub async fn request_account_logs_since(
+        &self,
+        uid: Option<usize>,
+        requests: &[(&Pool, Option<u64>)],
+    ) -> anyhow::Result<Vec<FetchedLog>> {
+        let mp = MultiProgress::new();
+        let style = SPINNER_STYLE
+            .clone()
+            .template("{prefix:.bold.dim}{spinner:.green} {msg}加载{pos}次抽卡记录");
+        // all the spinners must be added before waiting on the multi progress bar
+        let tasks: Vec<_> = requests
+            .iter()
+            .enumerate()
+            .map(|(index, (pool, last_id))| {
+                let pb = mp.add(ProgressBar::new_spinner().with_style(style.clone()));
+                pb.enable_steady_tick(5);
+                pb.set_prefix(&format!("[{}/{}] {} ", index + 1, requests.len(), pool));
+                self.request_gacha_log_with_progress(pool, *last_id, uid, pb)
             })
-            // flatten all the pages into one big iterator
-            .try_flatten()
-            .try_collect()
-            .await?;
+            .collect();
+        let progress_task = spawn_blocking(move || mp.join());
+        let (logs, _) = tokio::join!(future::try_join_all(tasks), progress_task);
+        logs
+    }
+
+    /// Get a chronological log of the pulls from `pool` newer than `last_id`, reporting
+    /// the progress on `pb`. The progress is saved to a checkpoint after every page, and an
+    /// unfinished fetch is resumed from its checkpoint. Checkpoints of the account `uid`
+    /// are kept in a directory of its own. Ctrl-C stops the fetch, keeping what is fetched
+    /// so far in the checkpoint if checkpoints are enabled
+    async fn request_gacha_log_with_progress(
+        &self,
+        pool: &Pool,
+        last_id: Option<u64>,
+        uid: Option<usize>,
+        pb: ProgressBar,
+    ) -> anyhow::Result<FetchedLog> {
+        let checkpoint_dir = self.checkpoint_dir.as_ref().map(|root| match uid {
+            Some(uid) => root.join(uid.to_string()),
+            None => root.clone(),
+        });
+        let checkpoint_dir = checkpoint_dir.as_deref();
+        let mut progress = match checkpoint_dir {
+            Some(root) => Checkpoint::load(root, &pool.key)?,
+            None => None,
+        }
+        .unwrap_or_else(|| Checkpoint::new(&pool.key));
+        // the checkpoint may be left by a fetch of more pulls, what is already known is not
+        // wanted. Paging starts over if nothing new is left, as the cursor is past `last_id`
+        progress.pulls.retain(|pull| pull.id > last_id);
+        progress.skipped.retain(|id| Some(*id) > last_id);
+        if progress.pulls.is_empty() {
+            progress = Checkpoint::new(&pool.key);
+        }
+        let resumed = !progress.pulls.is_empty();
+        pb.set_position(progress.pulls.len() as u64);
+        pb.set_message(if resumed {
+            "正在从断点继续加载，已"
+        } else {
+            "正在加载，已"
+        });
+        // number of retries so far, shown on the progress bar
+        let retries = Cell::new(0u32);
+
+        let fetch = async {
+            // pulls made after the interrupted run, they are not part of the checkpoint
+            let mut head = Checkpoint::new(&pool.key);
+            head.uid = progress.uid;
+            if resumed {
+                let newest = progress.pulls.first().and_then(|pull| pull.id);
+                self.page_log(pool, newest.max(last_id), &mut head, None, &retries, &pb)
+                    .await?;
+            }
+            self.page_log(pool, last_id, &mut progress, checkpoint_dir, &retries, &pb)
+                .await?;
+            head.pulls.append(&mut progress.pulls);
+            head.skipped.append(&mut progress.skipped);
+            Ok::<_, anyhow::Error>(head)
+        };
+        let _interruptible = InterruptibleFetch::start();
+        let fetched = tokio::select! {
+            fetched = fetch => fetched?,
+            _ = ctrl_c() => {
+                pb.abandon_with_message("已中断，已");
+                return Err(match checkpoint_dir {
+                    Some(_) => anyhow!(
+                        "获取被中断，已加载的{}条记录保存在断点中，下次获取时将从断点继续",
+                        pb.position()
+                    ),
+                    None => anyhow!("获取被中断，未使用断点，已加载的{}条记录已丢弃", pb.position()),
+                });
+            }
+        };
+        if let Some(root) = checkpoint_dir {
+            Checkpoint::remove(root, &pool.key)?;
+        }
+        let mut pull_list = fetched.pulls;
         // reverse the list so that the log is chronological
         pull_list.reverse();
         // finish the progress bar
         pb.set_length(pull_list.len() as u64);
-        pb.finish_with_message("已");
-        Ok(pull_list)
+        if retries.get() > 0 {
+            pb.finish_with_message(&format!("共重试{}次，已", retries.get()));
+        } else {
+            pb.finish_with_message("已");
+        }
+        Ok(FetchedLog {
+            pulls: pull_list,
+            skipped: fetched.skipped,
+        })
+    }
+
+    /// Page through the log of `pool` from the cursor of `progress` towards older pulls,
+    /// pushing the pulls newer than `last_id` to `progress`. Paging stops at the end of the
+    /// log or as soon as `last_id` is reached. With `checkpoint_dir`, `progress` is saved
+    /// after every page
+    async fn page_log(
+        &self,
+        pool: &Pool,
+        last_id: Option<u64>,
+        progress: &mut Checkpoint,
+        checkpoint_dir: Option<&Path>,
+        retries: &Cell<u32>,
+        pb: &ProgressBar,
+    ) -> anyhow::Result<()> {
+        for page in 1.. {
+            // get records from current page
+            let query = vec![
+                ("init_type".to_owned(), pool.key.clone()),
+                ("gacha_type".to_owned(), pool.key.clone()),
+                ("size".to_owned(), "20".to_owned()),
+                ("page".to_owned(), page.to_string()),
+                ("end_id".to_owned(), progress.end_id.to_string()),
+            ];
+            let page = self.request_page(query, retries, pb).await?;
+            // stop when a page is empty, indicating end of log
+            let mut done = page.list.is_empty();
+            for pull in page.list {
+                // stop at the first pull that is already known, no further page will be requested
+                if Some(pull.id) <= last_id {
+                    done = true;
+                    break;
+                }
+                // never mix up logs of different accounts
+                if progress.uid != 0 && progress.uid != pull.uid {
+                    return Err(anyhow!(
+                        "断点中的抽卡记录属于UID {}，与当前账号UID {}不符，请删除断点目录后重试",
+                        progress.uid,
+                        pull.uid
+                    ));
+                }
+                progress.uid = pull.uid;
+                progress.end_id = pull.id;
+                let id = pull.id;
+                match self.convert_pull(pull) {
+                    Ok(pull) => {
+                        progress.pulls.push(pull);
+                        pb.inc(1);
+                    }
+                    Err(err) if self.skip_bad_records => {
+                        pb.println(format!("{} {}，已跳过", style("[警告]").red(), err));
+                        progress.skipped.push(id);
+                    }
+                    Err(err) => {
+                        return Err(err)
+                            .context("抽卡记录有误，可以使用--skip-bad-records跳过无法解析的记录");
+                    }
+                }
+            }
+            if let Some(root) = checkpoint_dir {
+                progress.save(root)?;
+            }
+            if done {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert a pull from API format to our format
+    fn convert_pull(&self, pull: GachaResult) -> Result<Pull, RecordError> {
+        let error = |kind| RecordError {
+            kind,
+            record: serde_json::to_string(&pull).unwrap_or_default(),
+        };
+        let rarity = pull
+            .item
+            .rank_type
+            .parse()
+            .ok()
+            .and_then(Rarity::from_rank)
+            .ok_or_else(|| error(RecordErrorKind::Rarity(pull.item.rank_type.clone())))?;
+        let item_type = ItemType::recognize(&pull.item.item_type, &pull.item.item_id)
+            .ok_or_else(|| error(RecordErrorKind::ItemType(pull.item.item_type.clone())))?;
+        let time = region_offset(&self.base_query.region)
+            .datetime_from_str(&pull.time, "%Y-%m-%d %T")
+            .map_err(|_| error(RecordErrorKind::Time(pull.time.clone())))?;
+        let count = pull
+            .count
+            .parse()
+            .map_err(|_| error(RecordErrorKind::Count(pull.count.clone())))?;
+        Ok(Pull {
+            id: Some(pull.id),
+            uid: pull.uid,
+            gacha_type: pull.gacha_type,
+            count,
+            lang: pull.lang,
+            time,
+            item: Item {
+                item_id: pull.item.item_id,
+                name: pull.item.name,
+                rarity,
+                item_type,
+            },
+        })
+    }
+
+    /// Request a page of gacha log, retrying transient failures with exponential backoff.
+    /// `retries` counts the retries made for the whole log and is shown on `pb`
+    async fn request_page(
+        &self,
+        query: Vec<(String, String)>,
+        retries: &Cell<u32>,
+        pb: &ProgressBar,
+    ) -> anyhow::Result<GachaResultPage> {
+        let endpoint = format!("{}/getGachaLog", self.base_url);
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.wait().await;
+            match Self::issue_api(&*self.transport, &self.base_query, &endpoint, query.clone())
+                .await
+            {
+                Err(err) if attempt < self.max_retries && is_transient(&err) => {
+                    retries.set(retries.get() + 1);
+                    pb.set_message(&format!("正在加载（已重试{}次），已", retries.get()));
+                    sleep(retry_delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
     }
 
     /// Verify whether a url pointing to a gacha page contains proper query compoenent
@@ -272,74 +898,75 @@ impl Client {
         BaseQuery::new(url).is_ok()
     }
 
-    /// Add a spinner to a multi progress bar
-    fn add_spinner(mp: &MultiProgress, step: usize, total: usize) -> ProgressBar {
-        let style = SPINNER_STYLE
-            .clone()
-            .template("{prefix:.bold.dim}{spinner:.green} {msg}");
-        let pb = mp.add(ProgressBar::new_spinner().with_style(style));
-        pb.enable_steady_tick(5);
-        pb.set_prefix(&format!("[{}/{}]", step, total));
-        pb
-    }
-
-    /// Get a list of pools that can be queried
+    /// Get a list of pools that can be queried. If the endpoint of the list does not exist, each
+    /// pool in `catalogue` is probed and those with any pull are listed
     async fn request_pools(
-        client: &ReqClient,
+        transport: &dyn Transport,
         base_query: &BaseQuery,
         base_url: &str,
+        catalogue: &[CatalogueEntry],
+        rate_limiter: &RateLimiter,
         pb: ProgressBar,
     ) -> anyhow::Result<Vec<Pool>> {
         pb.set_message("加载卡池列表");
-        let config_list: ConfigListData = Self::issue_api(
-            client,
+        rate_limiter.wait().await;
+        let config_list: anyhow::Result<ConfigListData> = Self::issue_api(
+            transport,
             base_query,
             &format!("{}/getConfigList", base_url),
             None,
         )
-        .await?;
-        pb.finish_with_message("已加载卡池列表");
-        Ok(config_list
-            .gacha_type_list
-            .into_iter()
-            .map(Into::into)
-            .collect())
-    }
+        .await;
+        match config_list {
+            Ok(config_list) => {
+                pb.finish_with_message("已加载卡池列表");
+                return Ok(config_list
+                    .gacha_type_list
+                    .into_iter()
+                    .map(Into::into)
+                    .collect());
+            }
+            // the endpoint is gone in some versions of the game, fall back to the catalogue
+            Err(err) if is_not_found(&err) => (),
+            Err(err) => return Err(err),
+        }
 
-    /// Get the identifier for weapon and character
-    async fn request_items(
-        client: &ReqClient,
-        base_query: &BaseQuery,
-        pb: ProgressBar,
-    ) -> anyhow::Result<(String, String)> {
-        pb.set_message("加载图鉴");
-        // get region/lang specific url
-        let url = item_list_url(&base_query.region, &base_query.lang);
-        let item_list = client
-            .get(url)
-            .send()
-            .await?
-            .json::<Vec<GachaItem>>()
-            .await?;
-        let weapon_identifier = item_list
-            .iter()
-            .find(|item| item.item_id == WEAPON_ID)
-            .ok_or(anyhow!("内置的绝弦ID已过期，无法建立图鉴"))?
-            .item_type
-            .clone();
-        let character_identifier = item_list
-            .iter()
-            .find(|item| item.item_id == CHARACTER_ID)
-            .ok_or(anyhow!("内置的温迪ID已过期，无法建立图鉴"))?
-            .item_type
-            .clone();
-        pb.finish_with_message("已加载图鉴");
-        Ok((weapon_identifier, character_identifier))
+        let mut pools = vec![];
+        for (index, entry) in catalogue.iter().enumerate() {
+            pb.set_message(&format!(
+                "无法加载卡池列表，正在检查内置卡池({}/{})",
+                index + 1,
+                catalogue.len()
+            ));
+            rate_limiter.wait().await;
+            let page: anyhow::Result<GachaResultPage> = Self::issue_api(
+                transport,
+                base_query,
+                &format!("{}/getGachaLog", base_url),
+                vec![
+                    ("init_type".to_owned(), entry.key.clone()),
+                    ("gacha_type".to_owned(), entry.key.clone()),
+                    ("size".to_owned(), "1".to_owned()),
+                    ("page".to_owned(), "1".to_owned()),
+                    ("end_id".to_owned(), "0".to_owned()),
+                ],
+            )
+            .await;
+            match page {
+                Ok(page) if !page.list.is_empty() => pools.push(entry.to_pool(&base_query.lang)),
+                Ok(_) => (),
+                // keys unknown to the server are rejected with some unknown retcode
+                Err(err) if matches!(err.downcast_ref(), Some(ApiError::Unknown { .. })) => (),
+                Err(err) => return Err(err),
+            }
+        }
+        pb.finish_with_message(&format!("已检查内置卡池，{}个卡池有抽卡记录", pools.len()));
+        Ok(pools)
     }
 
     /// Get response from Genshin API server
     async fn issue_api<T, Q, K, V>(
-        client: &ReqClient,
+        transport: &dyn Transport,
         base_query: &BaseQuery,
         endpoint: &str,
         additional_query: Q,
@@ -351,19 +978,11 @@ impl Client {
     {
         // build query component
         let mut query = base_query.as_hashmap();
-        query.extend(additional_query.into_iter());
-        let url = Url::parse_with_params(endpoint, query).unwrap();
-        let resp = client
-            .get(url)
-            .send()
-            .await?
-            .json::<ApiResponse<T>>()
-            .await?;
-        if resp.retcode != 0 {
-            Err(anyhow!(resp.message))
-        } else {
-            Ok(resp.data.unwrap())
-        }
+        query.extend(additional_query);
+        let url = Url::parse_with_params(endpoint, query)
+            .with_context(|| format!("API网址无效: {}", endpoint))?;
+        let resp: ApiResponse<T> = get_json(transport, url).await?;
+        Ok(resp.into_result()?)
     }
 }
 
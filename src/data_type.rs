@@ -1,14 +1,78 @@
 use std::{fmt, hash::Hash};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, TimeZone};
 use enum_map::Enum;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Enum, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Enum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 pub enum ItemType {
     Weapon,
     Character,
 }
 
+/// Names of item types used by the API, as (language, weapon, character)
+const ITEM_TYPE_NAMES: &[(&str, &str, &str)] = &[
+    ("zh-cn", "武器", "角色"),
+    ("zh-tw", "武器", "角色"),
+    ("en-us", "Weapon", "Character"),
+    ("ja-jp", "武器", "キャラクター"),
+    ("ko-kr", "무기", "캐릭터"),
+    ("de-de", "Waffe", "Figur"),
+    ("es-es", "Arma", "Personaje"),
+    ("fr-fr", "Arme", "Personnage"),
+    ("id-id", "Senjata", "Karakter"),
+    ("pt-pt", "Arma", "Personagem"),
+    ("ru-ru", "Оружие", "Персонаж"),
+    ("th-th", "อาวุธ", "ตัวละคร"),
+    ("vi-vn", "Vũ Khí", "Nhân Vật"),
+];
+
+impl ItemType {
+    /// Name of the item type in `lang`, English is used for unknown languages
+    pub fn localized(&self, lang: &str) -> &'static str {
+        let (_, weapon, character) = ITEM_TYPE_NAMES
+            .iter()
+            .find(|(name_lang, _, _)| name_lang.eq_ignore_ascii_case(lang))
+            .unwrap_or(&ITEM_TYPE_NAMES[2]);
+        match self {
+            Self::Weapon => weapon,
+            Self::Character => character,
+        }
+    }
+
+    /// Recognize an item type from its name in any of the known languages
+    pub fn from_localized(name: &str) -> Option<Self> {
+        let name = name.trim();
+        ITEM_TYPE_NAMES.iter().find_map(|(_, weapon, character)| {
+            if weapon.eq_ignore_ascii_case(name) {
+                Some(Self::Weapon)
+            } else if character.eq_ignore_ascii_case(name) {
+                Some(Self::Character)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Recognize an item type from its id. Characters are numbered `1xxx` in item lists
+    /// and `1000xxxx` in gacha logs, weapons are numbered `1xxxx` with the weapon type as
+    /// the second digit
+    pub fn from_item_id(item_id: &str) -> Option<Self> {
+        match item_id.trim().parse::<u32>().ok()? {
+            1000..=1999 | 10_000_000..=10_999_999 => Some(Self::Character),
+            11_000..=15_999 => Some(Self::Weapon),
+            _ => None,
+        }
+    }
+
+    /// Recognize an item type from its name, or from its id if the name is unknown
+    pub fn recognize(name: &str, item_id: &str) -> Option<Self> {
+        Self::from_localized(name).or_else(|| Self::from_item_id(item_id))
+    }
+}
+
 impl fmt::Display for ItemType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -18,13 +82,36 @@ impl fmt::Display for ItemType {
     }
 }
 
-#[derive(Debug, Enum, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Enum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 pub enum Rarity {
     Three,
     Four,
     Five,
 }
 
+impl Rarity {
+    /// Convert from `rank_type` used by the API
+    pub fn from_rank(rank: u8) -> Option<Self> {
+        match rank {
+            5 => Some(Self::Five),
+            4 => Some(Self::Four),
+            3 => Some(Self::Three),
+            _ => None,
+        }
+    }
+
+    /// Convert to `rank_type` used by the API
+    pub fn rank(&self) -> u8 {
+        match self {
+            Self::Three => 3,
+            Self::Four => 4,
+            Self::Five => 5,
+        }
+    }
+}
+
 impl fmt::Display for Rarity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -36,30 +123,220 @@ impl fmt::Display for Rarity {
 }
 
 /// information of an item
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct Item {
+    /// id of the item, the API may leave it empty
+    #[serde(default)]
+    pub item_id: String,
     pub name: String,
     pub item_type: ItemType,
     pub rarity: Rarity,
 }
 
 /// result of a single gacha
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Pull {
-    pub time: DateTime<Local>,
+    /// unique id assigned by the API, increasing over time. Absent if the pull is
+    /// imported from a source without id
+    pub id: Option<u64>,
+    /// uid of the account making the pull
+    #[serde(default)]
+    pub uid: usize,
+    /// key of the pool the pull is made from
+    #[serde(default)]
+    pub gacha_type: String,
+    /// number of items obtained, always 1 so far
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// language of the names
+    #[serde(default)]
+    pub lang: String,
+    /// time of the pull with the offset of the server
+    pub time: DateTime<FixedOffset>,
     pub item: Item,
 }
 
+impl Pull {
+    /// Type of the pool the pull is made from, `None` if unknown
+    pub fn pool_kind(&self) -> Option<PoolKind> {
+        PoolKind::from_key(&self.gacha_type)
+    }
+
+    /// The pull with its time in the time zone of the server, told by uid. Older versions
+    /// store server times with the offset of the local machine, which is replaced while
+    /// the time of day is kept. Pulls already in server time are left as is
+    pub fn in_server_time(mut self) -> Self {
+        self.time = region_offset(uid_region(self.uid))
+            .from_local_datetime(&self.time.naive_local())
+            .unwrap();
+        self
+    }
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+/// Time zone of the server of `region` such as `cn_gf01` or `os_usa`, in which the API gives
+/// times. Servers other than America and Europe are in UTC+8
+pub fn region_offset(region: &str) -> FixedOffset {
+    match region {
+        "os_usa" => FixedOffset::west(5 * 3600),
+        "os_euro" => FixedOffset::east(3600),
+        _ => FixedOffset::east(8 * 3600),
+    }
+}
+
+/// Region of the server the account `uid` is on, told by the first digit of uid
+pub fn uid_region(uid: usize) -> &'static str {
+    match uid.to_string().chars().next() {
+        Some('5') => "cn_qd01",
+        Some('6') => "os_usa",
+        Some('7') => "os_euro",
+        Some('8') => "os_asia",
+        Some('9') => "os_cht",
+        _ => "cn_gf01",
+    }
+}
+
 /// information of a gacha pool
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Pool {
     pub id: usize,
     pub key: String,
     pub name: String,
 }
 
-impl ToString for Pool {
-    fn to_string(&self) -> String {
-        self.name.clone()
+impl Pool {
+    /// Type of the pool, `None` if the key is unknown
+    pub fn kind(&self) -> Option<PoolKind> {
+        PoolKind::from_key(&self.key)
+    }
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// pools queried together as a single pool, their logs are interleaved
+#[derive(Debug, Clone)]
+pub struct PoolGroup {
+    pub name: String,
+    pub pools: Vec<Pool>,
+}
+
+impl PoolGroup {
+    /// Group `pools` so that the character event wishes sharing pity become a single
+    /// group, other pools are kept on their own. The order of first appearance is kept
+    pub fn group(pools: &[Pool]) -> Vec<Self> {
+        let mut groups: Vec<Self> = vec![];
+        for pool in pools.iter() {
+            let shared = pool.kind() == Some(PoolKind::CharacterEvent);
+            match groups
+                .iter_mut()
+                .find(|group| shared && group.pools[0].kind() == Some(PoolKind::CharacterEvent))
+            {
+                Some(group) => {
+                    group.name = PoolKind::CharacterEvent.to_string();
+                    group.pools.push(pool.clone());
+                }
+                None => groups.push(Self {
+                    name: pool.name.clone(),
+                    pools: vec![pool.clone()],
+                }),
+            }
+        }
+        groups
+    }
+}
+
+/// Interleave the logs of different pools by time, pulls made at the same time are ordered
+/// by id
+pub fn interleave<I>(logs: I) -> Vec<Pull>
+where
+    I: IntoIterator<Item = Vec<Pull>>,
+{
+    let mut log: Vec<Pull> = logs.into_iter().flatten().collect();
+    log.sort_by_key(|pull| (pull.time, pull.id));
+    log
+}
+
+impl fmt::Display for PoolGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// type of a gacha pool, pools of the same type share pity and guarantee
+#[derive(
+    Debug, Enum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum PoolKind {
+    /// novice wish, key 100
+    Beginner,
+    /// standard wish, key 200
+    Standard,
+    /// character event wish, key 301 and 400
+    CharacterEvent,
+    /// weapon event wish, key 302
+    WeaponEvent,
+    /// chronicled wish, key 500
+    Chronicled,
+}
+
+impl PoolKind {
+    /// Type of the pool identified by `key`, i.e. `gacha_type` of a pull
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "100" => Some(Self::Beginner),
+            "200" => Some(Self::Standard),
+            "301" | "400" => Some(Self::CharacterEvent),
+            "302" => Some(Self::WeaponEvent),
+            "500" => Some(Self::Chronicled),
+            _ => None,
+        }
+    }
+
+    /// Number of pulls that guarantees a five-star
+    pub fn hard_pity(&self) -> usize {
+        match self {
+            Self::WeaponEvent => 80,
+            _ => 90,
+        }
+    }
+
+    /// Number of pulls that guarantees a four-star or above
+    pub fn four_star_pity(&self) -> usize {
+        10
+    }
+
+    /// Chance that a five-star is one of the rate-up items, `None` if there is no rate-up.
+    /// The rate-up item of chronicled wish is chosen by the player and not in the log, so
+    /// it is not considered as one
+    pub fn rate_up_chance(&self) -> Option<f64> {
+        match self {
+            Self::CharacterEvent => Some(0.5),
+            Self::WeaponEvent => Some(0.75),
+            _ => None,
+        }
+    }
+
+    /// Whether a five-star following a lost one is guaranteed to be a rate-up item
+    pub fn has_guarantee(&self) -> bool {
+        self.rate_up_chance().is_some()
+    }
+}
+
+impl fmt::Display for PoolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Beginner => write!(f, "新手祈愿"),
+            Self::Standard => write!(f, "常驻祈愿"),
+            Self::CharacterEvent => write!(f, "角色活动祈愿"),
+            Self::WeaponEvent => write!(f, "武器活动祈愿"),
+            Self::Chronicled => write!(f, "集录祈愿"),
+        }
     }
 }
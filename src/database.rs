@@ -0,0 +1,279 @@
+/// Local storage of gacha logs so that the history outlives the API
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{create_dir_all, rename, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    client::{Client, FetchedLog},
+    data_type::{interleave, Pool, PoolGroup, Pull},
+};
+
+/// Default location of the database, relative to the current directory
+pub const DEFAULT_DATABASE_DIR: &str = "gacha_history";
+
+/// A directory holding one append-only file per pool. Each line of a file is a pull
+/// serialized as JSON, and the lines are in chronological order
+#[derive(Debug)]
+pub struct Database {
+    root: PathBuf,
+}
+
+impl Database {
+    /// Open the database under `root`, the directory is created if it does not exist
+    pub fn open<P: Into<PathBuf>>(root: P) -> anyhow::Result<Self> {
+        let root = root.into();
+        create_dir_all(&root).with_context(|| format!("无法创建数据库目录 {}", root.display()))?;
+        Ok(Self { root })
+    }
+
+    /// Path of the file storing metadata of pools
+    fn pools_path(&self) -> PathBuf {
+        self.root.join("pools.json")
+    }
+
+    /// Load metadata of all the pools ever synced
+    pub fn load_pools(&self) -> anyhow::Result<Vec<Pool>> {
+        let path = self.pools_path();
+        match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))
+                .with_context(|| format!("数据库 {} 已损坏", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+            Err(err) => Err(err).with_context(|| format!("无法读取数据库 {}", path.display())),
+        }
+    }
+
+    /// Remember metadata of `pool`, replacing the old one with the same key
+    fn save_pool(&self, pool: &Pool) -> anyhow::Result<()> {
+        let mut pools = self.load_pools()?;
+        pools.retain(|stored| stored.key != pool.key);
+        pools.push(pool.clone());
+        pools.sort_by(|a, b| a.key.cmp(&b.key));
+        let path = self.pools_path();
+        let file =
+            File::create(&path).with_context(|| format!("无法打开数据库 {}", path.display()))?;
+        serde_json::to_writer_pretty(file, &pools)
+            .with_context(|| format!("无法写入数据库 {}", path.display()))
+    }
+
+    /// Path of the file storing pulls of pool identified by `key`
+    fn pool_path(&self, key: &str) -> PathBuf {
+        self.root.join(format!("{}.jsonl", key))
+    }
+
+    /// Load all the stored pulls of pool identified by `key` in chronological order. Times
+    /// stored by older versions in the local time zone are moved to the one of the server
+    pub fn load(&self, key: &str) -> anyhow::Result<Vec<Pull>> {
+        let path = self.pool_path(key);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => {
+                return Err(err).with_context(|| format!("无法读取数据库 {}", path.display()))
+            }
+        };
+        BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|(lineno, line)| {
+                let line = line.with_context(|| format!("无法读取数据库 {}", path.display()))?;
+                serde_json::from_str(&line)
+                    .map(Pull::in_server_time)
+                    .with_context(|| format!("数据库 {} 第{}行已损坏", path.display(), lineno + 1))
+            })
+            .collect()
+    }
+
+    /// Append `pulls` to the end of the stored log of pool identified by `key`
+    pub fn append(&self, key: &str, pulls: &[Pull]) -> anyhow::Result<()> {
+        let path = self.pool_path(key);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("无法打开数据库 {}", path.display()))?;
+        write_lines(BufWriter::new(file), pulls)
+            .with_context(|| format!("无法写入数据库 {}", path.display()))
+    }
+
+    /// Fetch pulls of `pool` newer than what is stored, save them and return the full history
+    pub async fn sync(&self, client: &Client, pool: &Pool) -> anyhow::Result<Vec<Pull>> {
+        let log = self.load(&pool.key)?;
+        let since = self.since(&pool.key, &log)?;
+        let fetched = client
+            .request_account_logs_since(uid(&[&log]), &[(pool, since)])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        self.save(pool, log, fetched)
+    }
+
+    /// Sync all of `pools` concurrently and return the full history of each of them
+    pub async fn sync_all(
+        &self,
+        client: &Client,
+        pools: &[Pool],
+    ) -> anyhow::Result<Vec<Vec<Pull>>> {
+        let logs = pools
+            .iter()
+            .map(|pool| self.load(&pool.key))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let requests = pools
+            .iter()
+            .zip(logs.iter())
+            .map(|(pool, log)| Ok((pool, self.since(&pool.key, log)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let stored: Vec<&[Pull]> = logs.iter().map(Vec::as_slice).collect();
+        let fetched = client
+            .request_account_logs_since(uid(&stored), &requests)
+            .await?;
+        pools
+            .iter()
+            .zip(logs)
+            .zip(fetched)
+            .map(|((pool, log), fetched)| self.save(pool, log, fetched))
+            .collect()
+    }
+
+    /// Path of the file storing the oldest skipped record of each pool
+    fn skipped_path(&self) -> PathBuf {
+        self.root.join("skipped.json")
+    }
+
+    /// Id of the oldest record skipped by the last sync of each pool, keyed by pool
+    fn load_skipped(&self) -> anyhow::Result<BTreeMap<String, u64>> {
+        let path = self.skipped_path();
+        match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))
+                .with_context(|| format!("数据库 {} 已损坏", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err).with_context(|| format!("无法读取数据库 {}", path.display())),
+        }
+    }
+
+    /// Remember the oldest record skipped by the last sync of pool identified by `key`
+    fn save_skipped(&self, key: &str, oldest: Option<u64>) -> anyhow::Result<()> {
+        let mut skipped = self.load_skipped()?;
+        let changed = match oldest {
+            Some(id) => skipped.insert(key.to_owned(), id) != Some(id),
+            None => skipped.remove(key).is_some(),
+        };
+        if !changed {
+            return Ok(());
+        }
+        let path = self.skipped_path();
+        let file =
+            File::create(&path).with_context(|| format!("无法打开数据库 {}", path.display()))?;
+        serde_json::to_writer_pretty(file, &skipped)
+            .with_context(|| format!("无法写入数据库 {}", path.display()))
+    }
+
+    /// Id after which pulls of pool identified by `key` are fetched. Usually it is the last
+    /// stored pull, but records skipped by the last sync are fetched again in case they
+    /// can be understood now
+    fn since(&self, key: &str, log: &[Pull]) -> anyhow::Result<Option<u64>> {
+        let last_id = last_id(log);
+        Ok(match self.load_skipped()?.get(key) {
+            Some(skipped) => last_id.map(|id| id.min(skipped.saturating_sub(1))),
+            None => last_id,
+        })
+    }
+
+    /// Replace the stored log of pool identified by `key` with `pulls` as a whole
+    fn rewrite(&self, key: &str, pulls: &[Pull]) -> anyhow::Result<()> {
+        let path = self.pool_path(key);
+        let temp_path = path.with_extension("jsonl.tmp");
+        let write = || -> io::Result<()> {
+            write_lines(BufWriter::new(File::create(&temp_path)?), pulls)?;
+            rename(&temp_path, &path)
+        };
+        write().with_context(|| format!("无法写入数据库 {}", path.display()))
+    }
+
+    /// Save the pulls `fetched` for `pool` after the stored `log`, and return the full history
+    fn save(
+        &self,
+        pool: &Pool,
+        mut log: Vec<Pull>,
+        fetched: FetchedLog,
+    ) -> anyhow::Result<Vec<Pull>> {
+        let mut new_pulls = fetched.pulls;
+        // never mix up logs of different accounts, records saved without uid are trusted
+        if let (Some(stored), Some(fetched)) = (log.first(), new_pulls.first()) {
+            if stored.uid != 0 && stored.uid != fetched.uid {
+                return Err(anyhow!(
+                    "数据库中的抽卡记录属于UID {}，与当前账号UID {}不符",
+                    stored.uid,
+                    fetched.uid
+                ));
+            }
+        }
+        // never store a pull twice
+        let mut known: HashSet<u64> = log.iter().filter_map(|pull| pull.id).collect();
+        new_pulls.retain(|pull| pull.id.map_or(true, |id| known.insert(id)));
+        // records skipped before and understood now may be older than what is stored
+        let in_order = match (log.last(), new_pulls.first()) {
+            (Some(stored), Some(new)) => (stored.time, stored.id) <= (new.time, new.id),
+            _ => true,
+        };
+        if in_order {
+            self.append(&pool.key, &new_pulls)?;
+            log.extend(new_pulls);
+        } else {
+            log.extend(new_pulls);
+            log.sort_by_key(|pull| (pull.time, pull.id));
+            self.rewrite(&pool.key, &log)?;
+        }
+        self.save_skipped(&pool.key, fetched.skipped.iter().min().copied())?;
+        self.save_pool(pool)?;
+        Ok(log)
+    }
+
+    /// Load the stored pulls of every pool in `group` interleaved by time
+    pub fn load_group(&self, group: &PoolGroup) -> anyhow::Result<Vec<Pull>> {
+        let logs = group
+            .pools
+            .iter()
+            .map(|pool| self.load(&pool.key))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(interleave(logs))
+    }
+
+    /// Sync every pool in `group` and interleave their histories by time, pulls made at
+    /// the same time are ordered by id
+    pub async fn sync_group(
+        &self,
+        client: &Client,
+        group: &PoolGroup,
+    ) -> anyhow::Result<Vec<Pull>> {
+        Ok(interleave(self.sync_all(client, &group.pools).await?))
+    }
+}
+
+/// Id of the last pull of `log` known to the API
+fn last_id(log: &[Pull]) -> Option<u64> {
+    log.iter().filter_map(|pull| pull.id).max()
+}
+
+/// Uid of the account the stored `logs` belong to, `None` if unknown
+fn uid(logs: &[&[Pull]]) -> Option<usize> {
+    logs.iter()
+        .flat_map(|log| log.iter())
+        .map(|pull| pull.uid)
+        .find(|uid| *uid != 0)
+}
+
+/// Write each pull as a line of JSON and make sure everything reaches the disk
+fn write_lines(mut output: BufWriter<File>, pulls: &[Pull]) -> io::Result<()> {
+    for pull in pulls.iter() {
+        serde_json::to_writer(&mut output, pull)?;
+        writeln!(output)?;
+    }
+    output.flush()?;
+    output.get_ref().sync_all()
+}
@@ -1,30 +1,104 @@
-use indicatif::ProgressBar;
 /// Functions that export a list of pulls to a file
-use std::{fs::File, io, io::Write, path::Path};
+use std::{
+    fs::File,
+    io,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Local};
+use indicatif::ProgressBar;
+use serde::Serialize;
 
-use crate::{data_type::Pull, style::SPINNER_STYLE};
+use crate::{
+    data_type::{Pool, Pull},
+    item_database,
+    style::SPINNER_STYLE,
+    uigf::UigfDocument,
+};
+
+/// Header of the csv file, files written by older versions only have the first four columns
+pub const CSV_HEADER: &str =
+    "抽卡时间,抽卡结果,类型,稀有度,ID,UID,卡池编号,数量,语言,物品ID,元素,武器类型";
+
+/// Format of time in exported files, ISO 8601 with the offset of the server
+pub const ISO_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+/// Document written by [`export_json`]
+#[derive(Debug, Serialize)]
+struct JsonExport<'a> {
+    /// uid of the account, absent if there is no pull
+    uid: Option<usize>,
+    export_time: DateTime<Local>,
+    pools: &'a [Pool],
+    pulls: &'a [Pull],
+}
 
 /// export a list of pulls into a csv file
 pub fn export_csv(results: &[Pull], path: &Path) -> io::Result<()> {
     let pb = ProgressBar::new_spinner()
         .with_style(SPINNER_STYLE.clone().template("{spinner:.green} {msg}"));
     pb.set_message("正在导出");
-    let mut output = File::create(path)?;
+    let mut output = BufWriter::new(File::create(path)?);
     // UTF-8 BOM
     output.write_all(&[0xEF, 0xBB, 0xBF])?;
-    writeln!(output, "抽卡时间,抽卡结果,类型,稀有度")?;
+    // fields with commas or quotes, such as some English names, are quoted
+    let mut output = csv::Writer::from_writer(output);
+    output.write_record(CSV_HEADER.split(','))?;
     pb.tick();
+    let database = item_database::current();
     for pull in results.iter() {
-        writeln!(
-            output,
-            "{},{},{},{}",
-            pull.time.format("%Y-%m-%d %T"),
-            pull.item.name,
-            pull.item.item_type,
-            pull.item.rarity
-        )?;
+        let info = database.get(&pull.item);
+        output.write_record(&[
+            pull.time.format(ISO_TIME_FORMAT).to_string(),
+            pull.item.name.clone(),
+            pull.item.item_type.to_string(),
+            pull.item.rarity.to_string(),
+            pull.id.map(|id| id.to_string()).unwrap_or_default(),
+            pull.uid.to_string(),
+            pull.gacha_type.clone(),
+            pull.count.to_string(),
+            pull.lang.clone(),
+            pull.item.item_id.clone(),
+            info.and_then(|info| info.element)
+                .map(|element| element.to_string())
+                .unwrap_or_default(),
+            info.map(|info| info.weapon_type.to_string())
+                .unwrap_or_default(),
+        ])?;
         pb.tick();
     }
+    output.flush()?;
+    pb.finish_with_message("导出完毕");
+    Ok(())
+}
+
+/// export a list of pulls made from `pools` into a json file, no information is lost
+pub fn export_json(pools: &[Pool], results: &[Pull], path: &Path) -> io::Result<()> {
+    let pb = ProgressBar::new_spinner()
+        .with_style(SPINNER_STYLE.clone().template("{spinner:.green} {msg}"));
+    pb.set_message("正在导出");
+    let mut output = BufWriter::new(File::create(path)?);
+    let document = JsonExport {
+        uid: results.first().map(|pull| pull.uid),
+        export_time: Local::now(),
+        pools,
+        pulls: results,
+    };
+    serde_json::to_writer_pretty(&mut output, &document)?;
+    output.flush()?;
+    pb.finish_with_message("导出完毕");
+    Ok(())
+}
+
+/// export a list of pulls into a UIGF json file
+pub fn export_uigf(results: &[Pull], path: &Path) -> io::Result<()> {
+    let pb = ProgressBar::new_spinner()
+        .with_style(SPINNER_STYLE.clone().template("{spinner:.green} {msg}"));
+    pb.set_message("正在导出");
+    let mut output = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut output, &UigfDocument::new(results, Local::now()))?;
+    output.flush()?;
     pb.finish_with_message("导出完毕");
     Ok(())
 }
@@ -0,0 +1,146 @@
+/// Functions that import a list of pulls from a file
+use std::{
+    fs::{read_to_string, File},
+    io::BufReader,
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, TimeZone};
+use serde::Deserialize;
+
+use crate::{
+    data_type::{region_offset, uid_region, Item, ItemType, Pull, Rarity},
+    export::{CSV_HEADER, ISO_TIME_FORMAT},
+    uigf::UigfDocument,
+};
+
+/// Number of columns in csv files written by older versions
+const LEGACY_CSV_COLUMNS: usize = 4;
+
+/// Format of time in csv files written by older versions, in the time zone of the server
+const LEGACY_CSV_TIME_FORMAT: &str = "%Y-%m-%d %T";
+
+/// Document written by [`export_json`], only the pulls are read back
+///
+/// [`export_json`]: crate::export::export_json
+#[derive(Debug, Deserialize)]
+struct JsonExport {
+    pulls: Vec<Pull>,
+}
+
+/// import a chronological list of pulls from a json file written by [`export_json`]
+///
+/// [`export_json`]: crate::export::export_json
+pub fn import_json(path: &Path) -> anyhow::Result<Vec<Pull>> {
+    let file = File::open(path).with_context(|| format!("无法打开文件 {}", path.display()))?;
+    let document: JsonExport = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("{} 不是本工具导出的JSON文件", path.display()))?;
+    Ok(document
+        .pulls
+        .into_iter()
+        .map(Pull::in_server_time)
+        .collect())
+}
+
+/// import a chronological list of pulls from a UIGF json file
+pub fn import_uigf(path: &Path) -> anyhow::Result<Vec<Pull>> {
+    let file = File::open(path).with_context(|| format!("无法打开文件 {}", path.display()))?;
+    let document: UigfDocument = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("{} 不是有效的UIGF文件", path.display()))?;
+    document.into_pulls()
+}
+
+/// import a chronological list of pulls from a csv file written by [`export_csv`],
+/// including the four-column files written by older versions. Pulls of such files do not
+/// record the pool and are left with an empty `gacha_type`
+///
+/// [`export_csv`]: crate::export::export_csv
+pub fn import_csv(path: &Path) -> anyhow::Result<Vec<Pull>> {
+    let content =
+        read_to_string(path).with_context(|| format!("无法读取文件 {}", path.display()))?;
+    // UTF-8 BOM
+    let content = content.trim_start_matches('\u{feff}');
+    let mut reader = csv::ReaderBuilder::new()
+        // rows written by older versions only have the first four columns
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let is_header_known = reader.headers().map_or(false, |header| {
+        header
+            .iter()
+            .zip(CSV_HEADER.split(','))
+            .take_while(|(column, expected)| column == expected)
+            .count()
+            >= LEGACY_CSV_COLUMNS
+    });
+    if !is_header_known {
+        return Err(anyhow!("{} 不是本工具导出的CSV文件", path.display()));
+    }
+    reader
+        .records()
+        .filter(|row| {
+            row.as_ref()
+                .map_or(true, |row| row.iter().any(|column| !column.is_empty()))
+        })
+        .map(|row| {
+            let row = row.with_context(|| format!("无法读取文件 {}", path.display()))?;
+            let lineno = row.position().map_or(0, |position| position.line());
+            parse_csv_row(&row)
+                .with_context(|| format!("无法解析 {} 第{}行", path.display(), lineno))
+        })
+        .collect()
+}
+
+/// Parse a row of the csv file into a pull, absent columns are left as unknown
+fn parse_csv_row(row: &csv::StringRecord) -> anyhow::Result<Pull> {
+    if row.len() < LEGACY_CSV_COLUMNS {
+        return Err(anyhow!(
+            "缺少数据: {}",
+            row.iter().collect::<Vec<_>>().join(",")
+        ));
+    }
+    let column = |index: usize| row.get(index).unwrap_or("");
+
+    let item_type = ItemType::from_localized(column(2))
+        .ok_or_else(|| anyhow!("无法识别的类型: {}", column(2)))?;
+    let rarity = column(3)
+        .parse()
+        .ok()
+        .and_then(Rarity::from_rank)
+        .ok_or_else(|| anyhow!("无效的稀有度: {}", column(3)))?;
+    let id = match column(4) {
+        "" => None,
+        id => Some(id.parse().with_context(|| format!("无效的ID: {}", id))?),
+    };
+    let uid = match column(5) {
+        "" => 0,
+        uid => uid.parse().with_context(|| format!("无效的UID: {}", uid))?,
+    };
+    let time = DateTime::parse_from_str(column(0), ISO_TIME_FORMAT)
+        .or_else(|_| {
+            region_offset(uid_region(uid)).datetime_from_str(column(0), LEGACY_CSV_TIME_FORMAT)
+        })
+        .with_context(|| format!("无效的时间: {}", column(0)))?;
+    let count = match column(7) {
+        "" => 1,
+        count => count
+            .parse()
+            .with_context(|| format!("无效的数量: {}", count))?,
+    };
+
+    Ok(Pull {
+        id,
+        uid,
+        gacha_type: column(6).to_owned(),
+        count,
+        lang: column(8).to_owned(),
+        time,
+        item: Item {
+            item_id: column(9).to_owned(),
+            name: column(1).to_owned(),
+            item_type,
+            rarity,
+        },
+    })
+}
@@ -0,0 +1,201 @@
+/// Metadata of items beyond what the API tells, bundled and optionally updated by a local file
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use enum_map::Enum;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::data_type::{Item, ItemType, Rarity};
+
+/// The bundled database
+const BUILTIN_DATABASE: &str = include_str!("item_database.json");
+
+lazy_static! {
+    /// The database in use, see [`install`]
+    static ref DATABASE: RwLock<Arc<ItemDatabase>> = RwLock::new(Arc::new(ItemDatabase::builtin()));
+}
+
+/// Element of a character
+#[derive(
+    Debug, Enum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Element {
+    Anemo,
+    Geo,
+    Electro,
+    Dendro,
+    Hydro,
+    Pyro,
+    Cryo,
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Anemo => write!(f, "风"),
+            Self::Geo => write!(f, "岩"),
+            Self::Electro => write!(f, "雷"),
+            Self::Dendro => write!(f, "草"),
+            Self::Hydro => write!(f, "水"),
+            Self::Pyro => write!(f, "火"),
+            Self::Cryo => write!(f, "冰"),
+        }
+    }
+}
+
+/// Type of a weapon, or of the weapon a character wields
+#[derive(
+    Debug, Enum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum WeaponType {
+    Sword,
+    Claymore,
+    Polearm,
+    Catalyst,
+    Bow,
+}
+
+impl fmt::Display for WeaponType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sword => write!(f, "单手剑"),
+            Self::Claymore => write!(f, "双手剑"),
+            Self::Polearm => write!(f, "长柄武器"),
+            Self::Catalyst => write!(f, "法器"),
+            Self::Bow => write!(f, "弓"),
+        }
+    }
+}
+
+/// Everything known about an item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemInfo {
+    /// id of the item as used in gacha logs
+    pub item_id: String,
+    pub item_type: ItemType,
+    pub rarity: Rarity,
+    /// element of a character, `None` for weapons
+    #[serde(default)]
+    pub element: Option<Element>,
+    pub weapon_type: WeaponType,
+    /// version of the game the item is released in, such as `1.3`
+    pub version: String,
+    /// whether the item is in the standard wish
+    pub standard: bool,
+    /// names of the item keyed by language such as `zh-cn`
+    pub names: HashMap<String, String>,
+}
+
+impl ItemInfo {
+    /// Name of the item in `lang`, English is used if the language is unknown
+    pub fn name(&self, lang: &str) -> &str {
+        self.names
+            .iter()
+            .find(|(name_lang, _)| name_lang.eq_ignore_ascii_case(lang))
+            .or_else(|| self.names.get_key_value("en-us"))
+            .map_or(&self.item_id, |(_, name)| name)
+    }
+
+    /// Short description of the item such as `火·长柄武器` or `弓`
+    pub fn tag(&self) -> String {
+        match self.element {
+            Some(element) => format!("{}·{}", element, self.weapon_type),
+            None => self.weapon_type.to_string(),
+        }
+    }
+}
+
+/// Content of a database file
+#[derive(Debug, Serialize, Deserialize)]
+struct DatabaseFile {
+    /// version of the game the database is up to
+    version: String,
+    items: Vec<ItemInfo>,
+}
+
+/// Items keyed by id
+#[derive(Debug)]
+pub struct ItemDatabase {
+    /// version of the game the database is up to
+    pub version: String,
+    items: HashMap<String, ItemInfo>,
+}
+
+impl ItemDatabase {
+    /// The database bundled in the binary
+    pub fn builtin() -> Self {
+        let file: DatabaseFile =
+            serde_json::from_str(BUILTIN_DATABASE).expect("内置物品数据库已损坏");
+        let mut database = Self {
+            version: String::new(),
+            items: HashMap::new(),
+        };
+        database.update(file);
+        database
+    }
+
+    /// The bundled database updated by the file at `path`. Items in the file replace the
+    /// bundled ones with the same id, and the version of the file is taken
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("无法读取物品数据库 {}", path.display()))?;
+        let file: DatabaseFile = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("物品数据库 {} 格式错误", path.display()))?;
+        let mut database = Self::builtin();
+        database.update(file);
+        Ok(database)
+    }
+
+    fn update(&mut self, file: DatabaseFile) {
+        self.version = file.version;
+        self.items.extend(
+            file.items
+                .into_iter()
+                .map(|info| (info.item_id.clone(), info)),
+        );
+    }
+
+    /// Look up `item`. Items are matched by id when the API provides one, and by name in
+    /// any language otherwise
+    pub fn get(&self, item: &Item) -> Option<&ItemInfo> {
+        if item.item_id.is_empty() {
+            let name = item.name.trim();
+            return self.items.values().find(|info| {
+                info.item_type == item.item_type
+                    && info
+                        .names
+                        .values()
+                        .any(|info_name| info_name.eq_ignore_ascii_case(name))
+            });
+        }
+        // item lists number characters as `1xxx` instead of `1000xxxx`
+        let item_id = match item.item_id.parse::<u32>() {
+            Ok(id @ 1000..=1999) => (id - 1000 + 10_000_000).to_string(),
+            _ => item.item_id.clone(),
+        };
+        self.items.get(&item_id)
+    }
+
+    /// Whether `item` is in the standard wish, `None` if the item is unknown
+    pub fn is_standard(&self, item: &Item) -> Option<bool> {
+        self.get(item).map(|info| info.standard)
+    }
+}
+
+/// Use `database` from now on instead of the bundled one
+pub fn install(database: ItemDatabase) {
+    *DATABASE.write().unwrap() = Arc::new(database);
+}
+
+/// The database in use
+pub fn current() -> Arc<ItemDatabase> {
+    DATABASE.read().unwrap().clone()
+}
@@ -0,0 +1,17 @@
+//! Export and analyze gacha logs of Genshin Impact
+pub mod banner_schedule;
+pub mod cassette;
+pub mod checkpoint;
+pub mod cli;
+pub mod client;
+pub mod data_type;
+pub mod database;
+pub mod export;
+pub mod import;
+pub mod item_database;
+pub mod merge;
+pub mod mitm;
+pub mod pool_catalogue;
+pub mod report;
+pub mod style;
+pub mod uigf;
@@ -1,10 +1,3 @@
-mod client;
-mod data_type;
-mod export;
-mod mitm;
-mod report;
-mod style;
-
 use std::{env::current_dir, path::PathBuf};
 
 use anyhow::{anyhow, Context};
@@ -12,18 +5,21 @@ use chrono::Local;
 use console::style;
 use dialoguer::{Confirm, Input, Select};
 use reqwest::Url;
+use structopt::StructOpt;
 
-use crate::{
-    client::Client,
-    export::export_csv,
+use genshin_gacha_exporter::{
+    cli::{self, Opt},
+    client::{Client, ClientConfig},
+    data_type::PoolGroup,
+    database::{Database, DEFAULT_DATABASE_DIR},
+    export::{export_csv, export_json},
     mitm::tap_for_url,
-    report::{summary::Summary, Report},
+    report::{banner::Banners, guarantee::Guarantee, summary::Summary, Report},
     style::{init as init_style, THEME},
 };
 
-async fn run() -> anyhow::Result<()> {
-    init_style();
-
+/// Interactive wizard used when no subcommand is given
+async fn run(config: &ClientConfig) -> anyhow::Result<()> {
     let url: Url = if Select::with_theme(&*THEME)
         .with_prompt("请选择模式")
         .item("代理模式： 启动HTTP代理自动获取网址")
@@ -50,13 +46,31 @@ async fn run() -> anyhow::Result<()> {
             .unwrap()
     };
 
-    let client = Client::new(url).await.context("初始化客户端失败")?;
-    let pools = client.get_pools();
+    let client = Client::new(url, config).await.context("初始化客户端失败")?;
+    // character event wishes share pity, so they are queried as one
+    let mut pools = PoolGroup::group(client.get_pools());
+    // every pool fetched concurrently, reported and exported together
+    pools.push(PoolGroup {
+        name: "全部卡池".to_owned(),
+        pools: client.get_pools().clone(),
+    });
+    // pulls are accumulated locally so that history older than what the API keeps survives,
+    // default being under cwd
+    let database_path = current_dir().unwrap_or_default().join(DEFAULT_DATABASE_DIR);
+    let database_path = Input::with_theme(&*THEME)
+        .with_prompt("抽卡记录数据库位置")
+        .validate_with(|path: &String| -> anyhow::Result<()> {
+            path.parse::<PathBuf>()?;
+            Ok(())
+        })
+        .with_initial_text(database_path.display().to_string())
+        .interact()?;
+    let database = Database::open(database_path)?;
 
     loop {
         let selection: usize = Select::with_theme(&*THEME)
             .with_prompt("请选择需要查询的卡池")
-            .items(pools)
+            .items(&pools)
             .item("退出")
             .default(0)
             .interact()?;
@@ -66,12 +80,17 @@ async fn run() -> anyhow::Result<()> {
             break;
         }
         let pool = &pools[selection];
-        let log = client
-            .request_gacha_log(pool)
+        let log = database
+            .sync_group(&client, pool)
             .await
             .context("获取抽卡记录失败")?;
         let summary = Summary::new(&log);
         summary.print();
+        // answer "am I on guarantee" for event wishes
+        let guarantee = Guarantee::new(&log);
+        guarantee.print();
+        let banners = Banners::new(&log);
+        banners.print();
 
         if Confirm::with_theme(&*THEME)
             .with_prompt("是否导出抽卡记录")
@@ -79,12 +98,25 @@ async fn run() -> anyhow::Result<()> {
             .default(true)
             .interact()?
         {
+            let extension = if Select::with_theme(&*THEME)
+                .with_prompt("请选择导出格式")
+                .item("CSV： 适合用表格软件查看")
+                .item("JSON： 包含全部信息，适合用程序处理")
+                .default(0)
+                .interact()?
+                == 0
+            {
+                "csv"
+            } else {
+                "json"
+            };
             // default being under cwd
             let mut save_path = current_dir().unwrap_or_default();
             save_path.push(format!(
-                "{}-{}.csv",
+                "{}-{}.{}",
                 Local::now().format("%Y-%m-%d %H-%M-%S"),
                 pool.name,
+                extension,
             ));
             let save_path = Input::with_theme(&*THEME)
                 .with_prompt("保存位置")
@@ -94,9 +126,14 @@ async fn run() -> anyhow::Result<()> {
                 })
                 .with_initial_text(save_path.display().to_string())
                 .interact()?;
-            // make sure the extension is csv
-            let save_path = PathBuf::from(save_path).with_extension("csv");
-            export_csv(&log, &save_path).context("保存文件失败")?;
+            // make sure the extension matches the format
+            let save_path = PathBuf::from(save_path).with_extension(extension);
+            if extension == "csv" {
+                export_csv(&log, &save_path)
+            } else {
+                export_json(&pool.pools, &log, &save_path)
+            }
+            .context("保存文件失败")?;
         }
     }
     Ok(())
@@ -104,13 +141,28 @@ async fn run() -> anyhow::Result<()> {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    init_style();
+
+    let config = opt.client_config();
+    let interactive = opt.command.is_none();
+    let result = match opt.install_item_database() {
+        Ok(()) => match opt.command {
+            Some(command) => cli::run(command, &config).await,
+            None => run(&config).await,
+        },
+        Err(err) => Err(err),
+    };
     // catch any error and display it
-    if let Err(err) = run().await {
+    if let Err(err) = result {
         eprintln!("{}{:?}", style("错误: ").red(), err);
-        Input::<String>::new()
-            .with_prompt("按回车键退出")
-            .allow_empty(true)
-            .interact()?;
+        // keep the console window open for the wizard
+        if interactive {
+            Input::<String>::new()
+                .with_prompt("按回车键退出")
+                .allow_empty(true)
+                .interact()?;
+        }
         Err(err)
     } else {
         Ok(())
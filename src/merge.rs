@@ -0,0 +1,103 @@
+/// Merge gacha logs from several sources into one
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::data_type::Pull;
+
+/// Result of merging several gacha logs
+#[derive(Debug, Default)]
+pub struct Merged {
+    /// chronological log without duplicates
+    pub pulls: Vec<Pull>,
+    /// number of pulls found in more than one source
+    pub overlaps: usize,
+    /// pulls that are considered the same but disagree with each other
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Two records that are considered the same pull but disagree with each other,
+/// the one seen first is kept
+#[derive(Debug)]
+pub struct Conflict {
+    pub kept: Pull,
+    pub dropped: Pull,
+}
+
+/// Identify a pull without API id by its time, name and the number of pulls with the
+/// same time and name before it in the same source, as a multi-pull shares the same time
+type FallbackKey = (DateTime<FixedOffset>, String, usize);
+
+/// Merge chronological logs, in order of priority. Pulls are deduplicated by API id when
+/// both sides have one, otherwise by [`FallbackKey`]
+pub fn merge<I>(logs: I) -> Merged
+where
+    I: IntoIterator<Item = Vec<Pull>>,
+{
+    let mut merged = Merged::default();
+    let mut by_id: HashMap<u64, usize> = HashMap::new();
+    let mut by_fallback: HashMap<FallbackKey, usize> = HashMap::new();
+
+    for log in logs {
+        let mut sequence: HashMap<(DateTime<FixedOffset>, String), usize> = HashMap::new();
+        for pull in log {
+            let seq = sequence
+                .entry((pull.time, pull.item.name.clone()))
+                .or_insert(0);
+            let fallback_key = (pull.time, pull.item.name.clone(), *seq);
+            *seq += 1;
+
+            let existing = match pull.id.and_then(|id| by_id.get(&id)) {
+                Some(index) => Some(*index),
+                // pulls with different ids are different pulls even if they look the same
+                None => by_fallback
+                    .get(&fallback_key)
+                    .copied()
+                    .filter(|index| pull.id.is_none() || merged.pulls[*index].id.is_none()),
+            };
+            match existing {
+                Some(index) => {
+                    merged.overlaps += 1;
+                    let kept = &mut merged.pulls[index];
+                    if !is_consistent(kept, &pull) {
+                        merged.conflicts.push(Conflict {
+                            kept: kept.clone(),
+                            dropped: pull,
+                        });
+                    } else if kept.id.is_none() && pull.id.is_some() {
+                        // the new record carries more information
+                        by_id.insert(pull.id.unwrap(), index);
+                        *kept = pull;
+                    }
+                }
+                None => {
+                    let index = merged.pulls.len();
+                    if let Some(id) = pull.id {
+                        by_id.insert(id, index);
+                    }
+                    by_fallback.entry(fallback_key).or_insert(index);
+                    merged.pulls.push(pull);
+                }
+            }
+        }
+    }
+
+    // stable sort keeps the order of pulls sharing the same time
+    merged.pulls.sort_by_key(|pull| pull.time);
+    merged
+}
+
+/// Whether two records of the same pull agree with each other, fields that are
+/// unknown on either side are not compared
+fn is_consistent(a: &Pull, b: &Pull) -> bool {
+    fn agree<T: PartialEq>(a: &T, b: &T, unknown: &T) -> bool {
+        a == unknown || b == unknown || a == b
+    }
+    agree(&a.id, &b.id, &None)
+        && agree(&a.uid, &b.uid, &0)
+        && agree(&a.gacha_type, &b.gacha_type, &String::new())
+        && a.time == b.time
+        && a.item.name == b.item.name
+        && a.item.item_type == b.item.item_type
+        && a.item.rarity == b.item.rarity
+}
@@ -5,12 +5,15 @@ use anyhow::anyhow;
 use reqwest::Url;
 use tokio::sync::oneshot;
 
+#[cfg(target_os = "windows")]
 use dialoguer::Confirm;
 use indicatif::ProgressBar;
 
+#[cfg(target_os = "windows")]
+use crate::style::THEME;
 use crate::{
     mitm::{cert::setup_certificate, service::make_mitm_server},
-    style::{SPINNER_STYLE, THEME},
+    style::SPINNER_STYLE,
 };
 
 pub const DOMAIN_INTERCEPT: &[&str] = &["hk4e-api.mihoyo.com", "hk4e-api-os.mihoyo.com"];
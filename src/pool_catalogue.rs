@@ -0,0 +1,114 @@
+/// Pools known without asking the API, used when the pool list is unavailable
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::data_type::Pool;
+
+/// Built-in pools, as (key, names in (language, name))
+const BUILTIN_POOLS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "100",
+        &[
+            ("zh-cn", "新手祈愿"),
+            ("zh-tw", "新手祈願"),
+            ("en-us", "Novice Wishes"),
+            ("ja-jp", "初心者向け祈願"),
+        ],
+    ),
+    (
+        "200",
+        &[
+            ("zh-cn", "常驻祈愿"),
+            ("zh-tw", "常駐祈願"),
+            ("en-us", "Standard Wish"),
+            ("ja-jp", "通常祈願"),
+        ],
+    ),
+    (
+        "301",
+        &[
+            ("zh-cn", "角色活动祈愿"),
+            ("zh-tw", "角色活動祈願"),
+            ("en-us", "Character Event Wish"),
+            ("ja-jp", "イベント祈願・キャラクター"),
+        ],
+    ),
+    (
+        "302",
+        &[
+            ("zh-cn", "武器活动祈愿"),
+            ("zh-tw", "武器活動祈願"),
+            ("en-us", "Weapon Event Wish"),
+            ("ja-jp", "イベント祈願・武器"),
+        ],
+    ),
+    (
+        "400",
+        &[
+            ("zh-cn", "角色活动祈愿-2"),
+            ("zh-tw", "角色活動祈願-2"),
+            ("en-us", "Character Event Wish-2"),
+            ("ja-jp", "イベント祈願・キャラクター2"),
+        ],
+    ),
+    (
+        "500",
+        &[
+            ("zh-cn", "集录祈愿"),
+            ("zh-tw", "集錄祈願"),
+            ("en-us", "Chronicled Wish"),
+            ("ja-jp", "集録祈願"),
+        ],
+    ),
+];
+
+/// A pool in the catalogue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogueEntry {
+    pub key: String,
+    /// names of the pool keyed by language such as `zh-cn`
+    pub names: HashMap<String, String>,
+}
+
+impl CatalogueEntry {
+    /// Name of the pool in `lang`, English or the key is used if the language is unknown
+    pub fn name(&self, lang: &str) -> String {
+        self.names
+            .iter()
+            .find(|(name_lang, _)| name_lang.eq_ignore_ascii_case(lang))
+            .or_else(|| self.names.get_key_value("en-us"))
+            .map_or_else(|| self.key.clone(), |(_, name)| name.clone())
+    }
+
+    /// The pool with its name in `lang`. The API uses the key as id as well
+    pub fn to_pool(&self, lang: &str) -> Pool {
+        Pool {
+            id: self.key.parse().unwrap_or_default(),
+            key: self.key.clone(),
+            name: self.name(lang),
+        }
+    }
+}
+
+/// The built-in catalogue
+pub fn builtin() -> Vec<CatalogueEntry> {
+    BUILTIN_POOLS
+        .iter()
+        .map(|(key, names)| CatalogueEntry {
+            key: (*key).to_owned(),
+            names: names
+                .iter()
+                .map(|(lang, name)| ((*lang).to_owned(), (*name).to_owned()))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Load a catalogue from a JSON file holding a list of [`CatalogueEntry`]
+pub fn load(path: &Path) -> anyhow::Result<Vec<CatalogueEntry>> {
+    let file = File::open(path).with_context(|| format!("无法读取卡池列表 {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("卡池列表 {} 格式错误", path.display()))
+}
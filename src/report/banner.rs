@@ -0,0 +1,142 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use chrono::NaiveDateTime;
+use console::StyledObject;
+use enum_map::EnumMap;
+
+use crate::{
+    banner_schedule::{Banner, BannerSchedule},
+    data_type::{PoolKind, Pull, Rarity},
+    item_database,
+    report::{stylizer, Report},
+};
+
+/// Pulls, five-stars and rate-up hits of every event banner, using the bundled schedule
+#[derive(Debug)]
+pub struct Banners {
+    /// stats of the banners with any pull, in chronological order
+    pub banners: Vec<StatsForBanner>,
+    /// number of pulls from event wishes made at times no known banner runs
+    pub unknown: usize,
+}
+
+/// Stats of a single banner
+#[derive(Debug)]
+pub struct StatsForBanner {
+    pub banner: Banner,
+    /// total number of pulls
+    pub len: usize,
+    /// number of items of correspondent rarity
+    pub num_per_rarity: EnumMap<Rarity, usize>,
+    /// number of rate-up items of correspondent rarity
+    pub rate_up_per_rarity: EnumMap<Rarity, usize>,
+}
+
+impl StatsForBanner {
+    fn new(banner: &Banner) -> Self {
+        Self {
+            banner: banner.clone(),
+            len: 0,
+            num_per_rarity: EnumMap::new(),
+            rate_up_per_rarity: EnumMap::new(),
+        }
+    }
+
+    fn update(&mut self, pull: &Pull, rate_up: bool) {
+        self.len += 1;
+        self.num_per_rarity[pull.item.rarity] += 1;
+        if rate_up {
+            self.rate_up_per_rarity[pull.item.rarity] += 1;
+        }
+    }
+
+    /// Share of rate-up items among the items of `rarity`, `None` if nothing of `rarity` is
+    /// pulled or the rate-up items are unknown
+    pub fn rate_up_rate(&self, rarity: Rarity) -> Option<f64> {
+        let num = self.num_per_rarity[rarity];
+        if num == 0 || self.banner.rate_ups(rarity).is_empty() {
+            None
+        } else {
+            Some(self.rate_up_per_rarity[rarity] as f64 / num as f64)
+        }
+    }
+}
+
+impl Banners {
+    /// pretty print the stats of banners
+    fn write_to<T: Write>(&self, output: &mut T, with_style: bool) -> io::Result<()> {
+        let stylizer = stylizer(with_style);
+
+        for stats in self.banners.iter() {
+            writeln!(
+                output,
+                "{} {}：",
+                stylizer(stats.banner.title()).with_style(StyledObject::bold),
+                stats.banner.kind,
+            )?;
+            writeln!(
+                output,
+                "  共{}抽，五星{}个，四星{}个",
+                stylizer(stats.len.to_string()).with_style(StyledObject::blue),
+                stylizer(stats.num_per_rarity[Rarity::Five].to_string())
+                    .with_style(StyledObject::yellow),
+                stylizer(stats.num_per_rarity[Rarity::Four].to_string())
+                    .with_style(StyledObject::magenta),
+            )?;
+            for (rarity, rarity_name) in [(Rarity::Five, "五星"), (Rarity::Four, "四星")].iter()
+            {
+                if let Some(rate) = stats.rate_up_rate(*rarity) {
+                    writeln!(
+                        output,
+                        "  {}UP{}个，UP率{}%",
+                        rarity_name,
+                        stylizer(stats.rate_up_per_rarity[*rarity].to_string())
+                            .with_style(StyledObject::green),
+                        stylizer(format!("{:.2}", rate * 100.0)).with_style(StyledObject::green),
+                    )?;
+                }
+            }
+        }
+        if self.unknown > 0 {
+            writeln!(
+                output,
+                "另有{}抽不在已知的活动祈愿时间内",
+                stylizer(self.unknown.to_string()).with_style(StyledObject::red),
+            )?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+impl Report for Banners {
+    fn new(log: &[Pull]) -> Self {
+        let schedule = BannerSchedule::builtin();
+        let database = item_database::current();
+        let mut banners: BTreeMap<(NaiveDateTime, PoolKind), StatsForBanner> = BTreeMap::new();
+        let mut unknown = 0;
+        for pull in log.iter() {
+            match schedule.banner_of(pull) {
+                Some(banner) => banners
+                    .entry((banner.start, banner.kind))
+                    .or_insert_with(|| StatsForBanner::new(banner))
+                    .update(pull, banner.is_rate_up(&pull.item, &database)),
+                None if pull.pool_kind().map_or(false, |kind| kind.has_guarantee()) => unknown += 1,
+                None => (),
+            }
+        }
+        Self {
+            banners: banners.into_iter().map(|(_, stats)| stats).collect(),
+            unknown,
+        }
+    }
+    fn print(&self) {
+        self.write_to(&mut io::stdout(), true).unwrap();
+    }
+    fn write<T: Write>(&self, output: &mut T) -> io::Result<()> {
+        self.write_to(output, false)
+    }
+}
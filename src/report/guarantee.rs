@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+
+use console::StyledObject;
+use enum_map::EnumMap;
+
+use crate::{
+    banner_schedule::BannerSchedule,
+    data_type::{Item, PoolKind, Pull, Rarity},
+    item_database,
+    report::{stylizer, Report},
+};
+
+/// Tracks the 50/50 of every five-star from event wishes and whether the next one is guaranteed
+#[derive(Debug)]
+pub struct Guarantee {
+    /// guarantee of each pool type, only those with guarantee are tracked
+    pub banners: EnumMap<PoolKind, GuaranteeForBanner>,
+}
+
+/// How a five-star from an event wish is obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// a rate-up item while not guaranteed
+    Won,
+    /// a standard item instead of a rate-up one
+    Lost,
+    /// a rate-up item because the last one is lost
+    Guaranteed,
+    /// the item is unknown, or follows an unknown one so that the guarantee is unknown
+    Unknown,
+}
+
+/// Guarantee of a type of event wish
+#[derive(Debug)]
+pub struct GuaranteeForBanner {
+    /// total number of pulls
+    pub len: usize,
+    /// five-stars along with how they are obtained, in chronological order
+    pub pulls: Vec<(Item, Outcome)>,
+    /// whether the next five-star is guaranteed to be a rate-up one, `None` if unknown
+    pub guaranteed: Option<bool>,
+}
+
+impl Default for GuaranteeForBanner {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            pulls: vec![],
+            guaranteed: Some(false),
+        }
+    }
+}
+
+/// Whether `pull` gets a rate-up item, `None` if the item is unknown. The banner the pull
+/// is made from tells, and items in the standard wish are taken as not rate-up outside
+/// known banners
+fn is_rate_up(pull: &Pull) -> Option<bool> {
+    let database = item_database::current();
+    let info = database.get(&pull.item);
+    match BannerSchedule::builtin().banner_of(pull) {
+        // items without id are only told apart by name
+        _ if info.is_none() && pull.item.item_id.is_empty() => None,
+        Some(banner) => Some(banner.is_rate_up(&pull.item, &database)),
+        None => info.map(|info| !info.standard),
+    }
+}
+
+impl GuaranteeForBanner {
+    fn update(&mut self, pull: &Pull) {
+        self.len += 1;
+        if pull.item.rarity != Rarity::Five {
+            return;
+        }
+        let rate_up = is_rate_up(pull);
+        let outcome = match (rate_up, self.guaranteed) {
+            (Some(false), _) => Outcome::Lost,
+            (Some(true), Some(false)) => Outcome::Won,
+            (Some(true), Some(true)) => Outcome::Guaranteed,
+            _ => Outcome::Unknown,
+        };
+        self.guaranteed = rate_up.map(|rate_up| !rate_up);
+        self.pulls.push((pull.item.clone(), outcome));
+    }
+
+    /// Count of pulls with `outcome`
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.pulls.iter().filter(|(_, o)| *o == outcome).count()
+    }
+}
+
+impl Guarantee {
+    /// pretty print the guarantee
+    fn write_to<T: Write>(&self, output: &mut T, with_style: bool) -> io::Result<()> {
+        let stylizer = stylizer(with_style);
+
+        for (kind, banner) in self.banners.iter() {
+            // nothing to say about wishes never made
+            if banner.len == 0 {
+                continue;
+            }
+            writeln!(output, "{}抽出的五星：", kind)?;
+            for (item, outcome) in banner.pulls.iter() {
+                let outcome = match outcome {
+                    Outcome::Won => stylizer("不歪".to_owned()).with_style(StyledObject::green),
+                    Outcome::Lost => stylizer("歪了".to_owned()).with_style(StyledObject::red),
+                    Outcome::Guaranteed => {
+                        stylizer("大保底".to_owned()).with_style(StyledObject::blue)
+                    }
+                    Outcome::Unknown => stylizer("未知".to_owned()).with_style(StyledObject::white),
+                };
+                writeln!(
+                    output,
+                    "  {}： {}",
+                    stylizer(item.name.clone()).with_style(StyledObject::yellow),
+                    outcome,
+                )?;
+            }
+            let (won, lost) = (banner.count(Outcome::Won), banner.count(Outcome::Lost));
+            if won + lost > 0 {
+                writeln!(
+                    output,
+                    "  小保底{}次，不歪{}次，不歪率{}%",
+                    stylizer((won + lost).to_string()).with_style(StyledObject::blue),
+                    stylizer(won.to_string()).with_style(StyledObject::green),
+                    stylizer(format!("{:.2}", won as f64 / (won + lost) as f64 * 100.0))
+                        .with_style(StyledObject::green),
+                )?;
+            }
+            writeln!(
+                output,
+                "  下一个五星{}",
+                match banner.guaranteed {
+                    Some(true) => {
+                        stylizer("为大保底，必定是UP".to_owned()).with_style(StyledObject::green)
+                    }
+                    Some(false) => {
+                        stylizer("为小保底，有可能歪".to_owned()).with_style(StyledObject::red)
+                    }
+                    None => stylizer("是否为大保底未知，有未能识别的五星".to_owned())
+                        .with_style(StyledObject::white),
+                },
+            )?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+impl Report for Guarantee {
+    fn new(log: &[Pull]) -> Self {
+        let mut banners: EnumMap<PoolKind, GuaranteeForBanner> = EnumMap::new();
+        for pull in log.iter() {
+            match pull.pool_kind() {
+                Some(kind) if kind.has_guarantee() => banners[kind].update(pull),
+                _ => (),
+            }
+        }
+        Self { banners }
+    }
+    fn print(&self) {
+        self.write_to(&mut io::stdout(), true).unwrap();
+    }
+    fn write<T: Write>(&self, output: &mut T) -> io::Result<()> {
+        self.write_to(output, false)
+    }
+}
@@ -1,13 +1,21 @@
+pub mod banner;
+pub mod guarantee;
+pub mod pity;
 pub mod summary;
 
-use std::io::{self, Write};
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use console::{style, StyledObject};
 
 use crate::data_type::Pull;
 
 /// Trait for generating analysis on gacha log
 pub trait Report {
     /// Creating the report from a list of pulls
-    fn new(log: &Vec<Pull>) -> Self;
+    fn new(log: &[Pull]) -> Self;
     /// Display report in the console, by default we use `write` to
     /// display non-styled report
     fn print(&self) {
@@ -16,3 +24,40 @@ pub trait Report {
     /// Write report without style to something that implements `Write`
     fn write<T: Write>(&self, output: &mut T) -> io::Result<()>;
 }
+
+/// Helper enum so that a string can be write both to console with style and to file without style
+pub(crate) enum StylizedString {
+    Styled(StyledObject<String>),
+    UnStyled(String),
+}
+
+impl fmt::Display for StylizedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Styled(obj) => write!(f, "{}", obj),
+            Self::UnStyled(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl StylizedString {
+    /// Apply style specified by `f`, if the variant is UnStyled, nothing will happen
+    pub(crate) fn with_style<F>(self, f: F) -> Self
+    where
+        F: FnOnce(StyledObject<String>) -> StyledObject<String>,
+    {
+        match self {
+            Self::Styled(obj) => Self::Styled(f(obj)),
+            s => s,
+        }
+    }
+}
+
+/// Create a function turning a string into [`StylizedString`], styled only if `with_style`
+pub(crate) fn stylizer(with_style: bool) -> Box<dyn Fn(String) -> StylizedString> {
+    if with_style {
+        Box::new(|s| StylizedString::Styled(style(s)))
+    } else {
+        Box::new(StylizedString::UnStyled)
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use console::StyledObject;
+use enum_map::EnumMap;
+
+use crate::{
+    data_type::{Item, PoolKind, Pull, Rarity},
+    report::{stylizer, Report},
+};
+
+/// Lists the pity at which every four-star and five-star is pulled. Each pool type keeps
+/// its own counters, so the log is split by [`PoolKind`] first
+#[derive(Debug)]
+pub struct Pity {
+    /// pity of each pool type, pulls from unknown pools come first
+    pub pools: Vec<PityForPool>,
+}
+
+/// Pity within a single pool type
+#[derive(Debug)]
+pub struct PityForPool {
+    /// the pool type, `None` if unknown
+    pub kind: Option<PoolKind>,
+    /// pity of correspondent rarity, three-star is never recorded
+    pub pity_per_rarity: EnumMap<Rarity, PityForRarity>,
+}
+
+/// Pity of a single rarity within a single pool type
+#[derive(Default, Debug)]
+pub struct PityForRarity {
+    /// items pulled along with their pity, in chronological order
+    pub pulls: Vec<(Item, usize)>,
+    /// number of pulls since the last item of this rarity
+    pub current: usize,
+}
+
+impl PityForRarity {
+    /// Average pity, `None` if nothing is pulled
+    pub fn average(&self) -> Option<f64> {
+        if self.pulls.is_empty() {
+            None
+        } else {
+            let total: usize = self.pulls.iter().map(|(_, pity)| pity).sum();
+            Some(total as f64 / self.pulls.len() as f64)
+        }
+    }
+
+    /// Median pity, `None` if nothing is pulled
+    pub fn median(&self) -> Option<f64> {
+        let mut pities: Vec<usize> = self.pulls.iter().map(|(_, pity)| *pity).collect();
+        pities.sort_unstable();
+        let len = pities.len();
+        match len {
+            0 => None,
+            _ if len % 2 == 1 => Some(pities[len / 2] as f64),
+            _ => Some((pities[len / 2 - 1] + pities[len / 2]) as f64 / 2.0),
+        }
+    }
+
+    /// Count a pull made while waiting for an item of `rarity`
+    fn update(&mut self, rarity: Rarity, pull: &Pull) {
+        self.current += 1;
+        if pull.item.rarity == rarity {
+            self.pulls.push((pull.item.clone(), self.current));
+        }
+        // a five-star also satisfies the guarantee of four-star
+        if pull.item.rarity >= rarity {
+            self.current = 0;
+        }
+    }
+}
+
+impl PityForPool {
+    fn new(kind: Option<PoolKind>) -> Self {
+        Self {
+            kind,
+            pity_per_rarity: EnumMap::new(),
+        }
+    }
+
+    fn update(&mut self, pull: &Pull) {
+        for rarity in [Rarity::Four, Rarity::Five].iter() {
+            self.pity_per_rarity[*rarity].update(*rarity, pull);
+        }
+    }
+}
+
+impl Pity {
+    /// pretty print the pity
+    fn write_to<T: Write>(&self, output: &mut T, with_style: bool) -> io::Result<()> {
+        let stylizer = stylizer(with_style);
+
+        for pool in self.pools.iter() {
+            let pool_name = pool
+                .kind
+                .map_or_else(|| "未知卡池".to_owned(), |kind| kind.to_string());
+            for (rarity, rarity_name, color) in [
+                (
+                    Rarity::Five,
+                    "五星",
+                    StyledObject::yellow as fn(StyledObject<String>) -> StyledObject<String>,
+                ),
+                (Rarity::Four, "四星", StyledObject::magenta),
+            ]
+            .iter()
+            {
+                let pity = &pool.pity_per_rarity[*rarity];
+                writeln!(output, "{}抽出的{}：", pool_name, rarity_name)?;
+                for (item, count) in pity.pulls.iter() {
+                    writeln!(
+                        output,
+                        "  {}： 第{}抽",
+                        stylizer(item.name.clone()).with_style(color),
+                        stylizer(count.to_string()).with_style(StyledObject::blue),
+                    )?;
+                }
+                if let (Some(average), Some(median)) = (pity.average(), pity.median()) {
+                    writeln!(
+                        output,
+                        "  平均{}抽，中位数{}抽",
+                        stylizer(format!("{:.2}", average)).with_style(StyledObject::blue),
+                        stylizer(format!("{:.1}", median)).with_style(StyledObject::blue),
+                    )?;
+                }
+                write!(
+                    output,
+                    "  目前已{}抽未抽出{}",
+                    stylizer(pity.current.to_string()).with_style(StyledObject::red),
+                    rarity_name,
+                )?;
+                if let Some(kind) = pool.kind {
+                    let hard_pity = match rarity {
+                        Rarity::Five => kind.hard_pity(),
+                        _ => kind.four_star_pity(),
+                    };
+                    write!(
+                        output,
+                        "，距离保底还有{}抽",
+                        stylizer(hard_pity.saturating_sub(pity.current).to_string())
+                            .with_style(StyledObject::green),
+                    )?;
+                }
+                writeln!(output)?;
+            }
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+impl Report for Pity {
+    fn new(log: &[Pull]) -> Self {
+        let pools = log
+            .iter()
+            .fold(BTreeMap::new(), |mut pools, pull| {
+                pools
+                    .entry(pull.pool_kind())
+                    .or_insert_with(|| PityForPool::new(pull.pool_kind()))
+                    .update(pull);
+                pools
+            })
+            .into_iter()
+            .map(|(_, pool)| pool)
+            .collect();
+        Self { pools }
+    }
+    fn print(&self) {
+        self.write_to(&mut io::stdout(), true).unwrap();
+    }
+    fn write<T: Write>(&self, output: &mut T) -> io::Result<()> {
+        self.write_to(output, false)
+    }
+}
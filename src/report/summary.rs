@@ -1,16 +1,16 @@
 use std::{
     cmp,
-    collections::HashMap,
-    fmt,
+    collections::{BTreeSet, HashMap},
     io::{self, Write},
 };
 
-use console::{style, StyledObject};
+use console::StyledObject;
 use enum_map::EnumMap;
 
 use crate::{
-    data_type::{Item, ItemType, Pull, Rarity},
-    report::Report,
+    data_type::{Item, ItemType, PoolKind, Pull, Rarity},
+    item_database::{self, ItemDatabase},
+    report::{stylizer, Report},
 };
 
 /// Contains a summary of basic stats regarding a gacha log
@@ -22,44 +22,17 @@ pub struct Summary {
     pub stats_per_rarity: EnumMap<Rarity, StatsForRarity>,
     /// stats of correspondent item type
     pub stats_per_type: EnumMap<ItemType, StatsForType>,
-}
-
-/// Helper enum so that a string can be write both to console with style and to file without style
-enum StylizedString {
-    Styled(StyledObject<String>),
-    UnStyled(String),
-}
-
-impl fmt::Display for StylizedString {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Styled(obj) => write!(f, "{}", obj),
-            Self::UnStyled(s) => write!(f, "{}", s),
-        }
-    }
-}
-
-impl StylizedString {
-    /// Apply style specified by `f`, if the variant is UnStyled, nothing will happen
-    fn with_style<F>(self, f: F) -> Self
-    where
-        F: FnOnce(StyledObject<String>) -> StyledObject<String>,
-    {
-        match self {
-            Self::Styled(obj) => Self::Styled(f(obj)),
-            s @ _ => s,
-        }
-    }
+    /// type of the pool if all the pulls are from the same type of pools
+    pub kind: Option<PoolKind>,
+    /// whether the pulls are from pools not sharing pity, in which case streaks and
+    /// droughts make no sense and are not shown
+    pub mixed: bool,
 }
 
 impl Summary {
     /// pretty print the summary
     fn write_to<T: Write>(&self, output: &mut T, with_style: bool) -> io::Result<()> {
-        let stylizer: Box<dyn Fn(String) -> StylizedString> = if with_style {
-            Box::new(|s| StylizedString::Styled(style(s)))
-        } else {
-            Box::new(|s| StylizedString::UnStyled(s))
-        };
+        let stylizer = stylizer(with_style);
 
         writeln!(
             output,
@@ -114,62 +87,95 @@ impl Summary {
             )
             .with_style(StyledObject::magenta),
         )?;
-        writeln!(
-            output,
-            "最多连续抽出{}个五星，连续抽出{}个四星",
-            stylizer(
-                self.stats_per_rarity[Rarity::Five]
-                    .longest_streak
-                    .to_string()
-            )
-            .with_style(StyledObject::yellow),
-            stylizer(
-                self.stats_per_rarity[Rarity::Four]
-                    .longest_streak
-                    .to_string()
-            )
-            .with_style(StyledObject::magenta),
-        )?;
-        writeln!(
-            output,
-            "最多{}抽未抽出五星，目前{}抽未抽出五星，{}抽未抽出四星",
-            stylizer(
-                self.stats_per_rarity[Rarity::Five]
-                    .longest_drought
-                    .to_string()
-            )
-            .with_style(StyledObject::red),
-            stylizer(
-                self.stats_per_rarity[Rarity::Five]
-                    .current_drought
-                    .to_string()
-            )
-            .with_style(StyledObject::red),
-            stylizer(
-                self.stats_per_rarity[Rarity::Four]
-                    .current_drought
-                    .to_string()
-            )
-            .with_style(StyledObject::red),
-        )?;
-        if self.stats_per_rarity[Rarity::Five].sorted_occurrence.len() > 0 {
+        // pity does not carry over between pools
+        if !self.mixed {
+            writeln!(
+                output,
+                "最多连续抽出{}个五星，连续抽出{}个四星",
+                stylizer(
+                    self.stats_per_rarity[Rarity::Five]
+                        .longest_streak
+                        .to_string()
+                )
+                .with_style(StyledObject::yellow),
+                stylizer(
+                    self.stats_per_rarity[Rarity::Four]
+                        .longest_streak
+                        .to_string()
+                )
+                .with_style(StyledObject::magenta),
+            )?;
+            writeln!(
+                output,
+                "最多{}抽未抽出五星，目前{}抽未抽出五星，{}抽未抽出四星",
+                stylizer(
+                    self.stats_per_rarity[Rarity::Five]
+                        .longest_drought
+                        .to_string()
+                )
+                .with_style(StyledObject::red),
+                stylizer(
+                    self.stats_per_rarity[Rarity::Five]
+                        .current_drought
+                        .to_string()
+                )
+                .with_style(StyledObject::red),
+                stylizer(
+                    self.stats_per_rarity[Rarity::Four]
+                        .current_drought
+                        .to_string()
+                )
+                .with_style(StyledObject::red),
+            )?;
+            if let Some(kind) = self.kind {
+                let five_star_pity = self.stats_per_rarity[Rarity::Five].current_drought;
+                // a five-star also satisfies the guarantee of four-star
+                let four_star_pity = cmp::min(
+                    five_star_pity,
+                    self.stats_per_rarity[Rarity::Four].current_drought,
+                );
+                writeln!(
+                    output,
+                    "{}距离五星保底还有{}抽，距离四星保底还有{}抽",
+                    kind,
+                    stylizer(kind.hard_pity().saturating_sub(five_star_pity).to_string())
+                        .with_style(StyledObject::green),
+                    stylizer(
+                        kind.four_star_pity()
+                            .saturating_sub(four_star_pity)
+                            .to_string()
+                    )
+                    .with_style(StyledObject::green),
+                )?;
+            }
+        }
+        let database = item_database::current();
+        if !self.stats_per_rarity[Rarity::Five]
+            .sorted_occurrence
+            .is_empty()
+        {
             writeln!(output, "抽出的五星次数：",)?;
             for (item, count) in self.stats_per_rarity[Rarity::Five].sorted_occurrence.iter() {
                 writeln!(
                     output,
-                    "  {}： {}次",
+                    "  {}{}： {}次",
                     stylizer(item.name.clone()).with_style(StyledObject::yellow),
+                    describe(&database, item),
                     stylizer(count.to_string()).with_style(StyledObject::blue),
                 )?;
             }
         }
-        if self.stats_per_rarity[Rarity::Four].sorted_occurrence.len() > 0 {
+        if !self.stats_per_rarity[Rarity::Four]
+            .sorted_occurrence
+            .is_empty()
+        {
             writeln!(output, "抽出的四星次数：",)?;
             for (item, count) in self.stats_per_rarity[Rarity::Four].sorted_occurrence.iter() {
                 writeln!(
                     output,
-                    "  {}： {}次",
+                    "  {}{}： {}次",
                     stylizer(item.name.clone()).with_style(StyledObject::magenta),
+                    describe(&database, item),
                     stylizer(count.to_string()).with_style(StyledObject::blue),
                 )?;
             }
@@ -180,7 +186,7 @@ impl Summary {
 }
 
 impl Report for Summary {
-    fn new(log: &Vec<Pull>) -> Self {
+    fn new(log: &[Pull]) -> Self {
         log.iter()
             .fold(IntermediateSummary::default(), |mut summary, pull| {
                 summary.update(pull);
@@ -196,6 +202,14 @@ impl Report for Summary {
     }
 }
 
+/// Element and weapon type of `item` in brackets, empty if the item is unknown
+fn describe(database: &ItemDatabase, item: &Item) -> String {
+    database
+        .get(item)
+        .map(|info| format!("（{}）", info.tag()))
+        .unwrap_or_default()
+}
+
 /// Intermediate summary while folding
 #[derive(Debug, Default)]
 struct IntermediateSummary {
@@ -205,6 +219,8 @@ struct IntermediateSummary {
     stats_per_rarity: EnumMap<Rarity, IntermediateStatsForRarity>,
     /// stats of correspondent item type
     stats_per_type: EnumMap<ItemType, StatsForType>,
+    /// types of pools seen
+    kinds: BTreeSet<Option<PoolKind>>,
 }
 
 impl IntermediateSummary {
@@ -214,21 +230,28 @@ impl IntermediateSummary {
             stats.update(rarity, pull);
         }
         self.stats_per_type[pull.item.item_type].update(pull);
+        self.kinds.insert(pull.pool_kind());
     }
 }
 
-impl Into<Summary> for IntermediateSummary {
-    fn into(self) -> Summary {
+impl From<IntermediateSummary> for Summary {
+    fn from(summary: IntermediateSummary) -> Self {
         let mut stats_per_rarity = EnumMap::new();
         stats_per_rarity.extend(
-            self.stats_per_rarity
+            summary
+                .stats_per_rarity
                 .into_iter()
                 .map(|(rarity, stats)| (rarity, stats.into())),
         );
-        Summary {
-            len: self.len,
+        Self {
+            len: summary.len,
             stats_per_rarity,
-            stats_per_type: self.stats_per_type,
+            stats_per_type: summary.stats_per_type,
+            mixed: summary.kinds.len() > 1,
+            kind: match summary.kinds.len() {
+                1 => summary.kinds.into_iter().next().unwrap(),
+                _ => None,
+            },
         }
     }
 }
@@ -257,7 +280,7 @@ struct IntermediateStatsForRarity {
     occurrences: HashMap<Item, usize>,
 }
 
-impl<'a> IntermediateStatsForRarity {
+impl IntermediateStatsForRarity {
     fn update(&mut self, rarity: Rarity, pull: &Pull) {
         if rarity == pull.item.rarity {
             self.num += 1;
@@ -273,16 +296,16 @@ impl<'a> IntermediateStatsForRarity {
     }
 }
 
-impl Into<StatsForRarity> for IntermediateStatsForRarity {
-    fn into(mut self) -> StatsForRarity {
-        let mut sorted_occurrence: Vec<(Item, usize)> = self.occurrences.drain().collect();
+impl From<IntermediateStatsForRarity> for StatsForRarity {
+    fn from(mut stats: IntermediateStatsForRarity) -> Self {
+        let mut sorted_occurrence: Vec<(Item, usize)> = stats.occurrences.drain().collect();
         sorted_occurrence.sort_by_key(|(_, cnt)| cmp::Reverse(*cnt));
-        StatsForRarity {
-            num: self.num,
-            current_streak: self.current_streak,
-            longest_streak: self.longest_streak,
-            current_drought: self.current_drought,
-            longest_drought: self.longest_drought,
+        Self {
+            num: stats.num,
+            current_streak: stats.current_streak,
+            longest_streak: stats.longest_streak,
+            current_drought: stats.current_drought,
+            longest_drought: stats.longest_drought,
             sorted_occurrence,
         }
     }
@@ -1,4 +1,6 @@
-use dialoguer::{console::style, theme::ColorfulTheme};
+#[cfg(target_os = "windows")]
+use dialoguer::console::style;
+use dialoguer::theme::ColorfulTheme;
 use indicatif::ProgressStyle;
 use lazy_static::{initialize, lazy_static};
 
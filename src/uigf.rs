@@ -0,0 +1,180 @@
+/// Uniformed Interchangeable GachaLog Format, a json format shared by community tools
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::data_type::{region_offset, uid_region, Item, ItemType, Pull, Rarity};
+
+/// Version of UIGF we produce
+pub const UIGF_VERSION: &str = "v2.2";
+
+/// Format of time used in UIGF, in the time zone of the server
+const TIME_FORMAT: &str = "%Y-%m-%d %T";
+
+/// A whole UIGF document
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UigfDocument {
+    pub info: UigfInfo,
+    pub list: Vec<UigfEntry>,
+}
+
+/// The `info` block of UIGF
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UigfInfo {
+    pub uid: String,
+    pub lang: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_app: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_app_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uigf_version: Option<String>,
+}
+
+/// A single pull in UIGF, every field is a string as in the API
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UigfEntry {
+    #[serde(default)]
+    pub gacha_type: String,
+    #[serde(default)]
+    pub item_id: String,
+    #[serde(default = "default_count")]
+    pub count: String,
+    pub time: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    pub item_type: String,
+    pub rank_type: String,
+    pub id: String,
+    pub uigf_gacha_type: String,
+}
+
+fn default_count() -> String {
+    "1".to_owned()
+}
+
+/// Map a pool key to `uigf_gacha_type`, the two character event wishes share one type
+pub fn uigf_gacha_type(key: &str) -> &str {
+    match key {
+        "400" => "301",
+        key => key,
+    }
+}
+
+impl UigfDocument {
+    /// Build a document from a chronological list of pulls
+    pub fn new(results: &[Pull], export_time: DateTime<Local>) -> Self {
+        let uid = results
+            .first()
+            .map(|pull| pull.uid.to_string())
+            .unwrap_or_default();
+        let lang = results
+            .first()
+            .map(|pull| pull.lang.clone())
+            .unwrap_or_else(|| "zh-cn".to_owned());
+        Self {
+            info: UigfInfo {
+                uid,
+                lang,
+                export_time: Some(export_time.format(TIME_FORMAT).to_string()),
+                export_timestamp: Some(export_time.timestamp()),
+                export_app: Some(env!("CARGO_PKG_NAME").to_owned()),
+                export_app_version: Some(env!("CARGO_PKG_VERSION").to_owned()),
+                uigf_version: Some(UIGF_VERSION.to_owned()),
+            },
+            list: results.iter().map(UigfEntry::from).collect(),
+        }
+    }
+
+    /// Convert the document into a chronological list of pulls
+    pub fn into_pulls(self) -> anyhow::Result<Vec<Pull>> {
+        let uid = match self.info.uid.trim() {
+            "" => 0,
+            uid => uid
+                .parse()
+                .with_context(|| format!("无效的UID: {}", self.info.uid))?,
+        };
+        let lang = self.info.lang;
+        let mut pulls = self
+            .list
+            .into_iter()
+            .map(|entry| {
+                let id = entry.id.clone();
+                entry
+                    .into_pull(uid, &lang)
+                    .with_context(|| format!("无法解析ID为{}的抽卡记录", id))
+            })
+            .collect::<anyhow::Result<Vec<Pull>>>()?;
+        // ids are increasing over time, which also orders pulls sharing the same time
+        pulls.sort_by_key(|pull| (pull.time, pull.id));
+        Ok(pulls)
+    }
+}
+
+impl From<&Pull> for UigfEntry {
+    fn from(pull: &Pull) -> Self {
+        Self {
+            gacha_type: pull.gacha_type.clone(),
+            item_id: pull.item.item_id.clone(),
+            count: pull.count.to_string(),
+            time: pull.time.format(TIME_FORMAT).to_string(),
+            name: pull.item.name.clone(),
+            lang: Some(pull.lang.clone()),
+            item_type: pull.item.item_type.localized(&pull.lang).to_owned(),
+            rank_type: pull.item.rarity.rank().to_string(),
+            id: pull.id.map(|id| id.to_string()).unwrap_or_default(),
+            uigf_gacha_type: uigf_gacha_type(&pull.gacha_type).to_owned(),
+        }
+    }
+}
+
+impl UigfEntry {
+    /// Convert the entry into a pull, `uid` and `lang` come from the `info` block
+    fn into_pull(self, uid: usize, lang: &str) -> anyhow::Result<Pull> {
+        let rank: u8 = self
+            .rank_type
+            .parse()
+            .with_context(|| format!("无效的稀有度: {}", self.rank_type))?;
+        let rarity = Rarity::from_rank(rank).ok_or_else(|| anyhow!("无效的稀有度: {}", rank))?;
+        let item_type = ItemType::recognize(&self.item_type, &self.item_id)
+            .ok_or_else(|| anyhow!("无法识别的类型: {}", self.item_type))?;
+        let id = match self.id.trim() {
+            "" => None,
+            id => Some(
+                id.parse()
+                    .with_context(|| format!("无效的ID: {}", self.id))?,
+            ),
+        };
+        let count = self
+            .count
+            .parse()
+            .with_context(|| format!("无效的数量: {}", self.count))?;
+        let time = region_offset(uid_region(uid))
+            .datetime_from_str(&self.time, TIME_FORMAT)
+            .with_context(|| format!("无效的时间: {}", self.time))?;
+        Ok(Pull {
+            id,
+            uid,
+            // UIGF files lacking `gacha_type` are better than nothing
+            gacha_type: if self.gacha_type.is_empty() {
+                self.uigf_gacha_type
+            } else {
+                self.gacha_type
+            },
+            count,
+            lang: self.lang.unwrap_or_else(|| lang.to_owned()),
+            time,
+            item: Item {
+                item_id: self.item_id,
+                name: self.name,
+                item_type,
+                rarity,
+            },
+        })
+    }
+}
@@ -0,0 +1,133 @@
+/// Tests of the banner schedule and the per-banner report
+mod common;
+
+use genshin_gacha_exporter::{
+    banner_schedule::BannerSchedule,
+    data_type::{ItemType, PoolKind, Rarity},
+    report::{banner::Banners, Report},
+};
+
+use crate::common::{item, pull};
+
+#[test]
+fn assigns_pulls_to_banners() {
+    let schedule = BannerSchedule::builtin();
+
+    let banner = schedule
+        .banner_of(&pull("400").at("2021-03-10 12:00:00").build())
+        .unwrap();
+    assert_eq!(banner.version, "1.3");
+    assert_eq!(banner.phase, 3);
+    assert_eq!(banner.kind, PoolKind::CharacterEvent);
+    assert_eq!(banner.five_stars, ["10000046"]);
+    assert_eq!(banner.title(), "1.3 赤团开时（第3期）");
+
+    // the last phase ends at 16:00 on the day the next starts
+    let banner = schedule
+        .banner_of(&pull("301").at("2021-03-02 15:00:00").build())
+        .unwrap();
+    assert_eq!(banner.five_stars, ["10000042"]);
+
+    let banner = schedule
+        .banner_of(&pull("302").at("2020-12-05 12:00:00").build())
+        .unwrap();
+    assert_eq!(banner.kind, PoolKind::WeaponEvent);
+    assert_eq!(banner.five_stars, ["13504", "12504"]);
+
+    let banner = schedule
+        .banner_of(&pull("302").at("2021-03-20 12:00:00").build())
+        .unwrap();
+    assert_eq!(banner.version, "1.4");
+    assert_eq!(banner.five_stars, ["15503", "11502"]);
+
+    // between banners, and from pools without banners
+    assert!(schedule
+        .banner_of(&pull("301").at("2020-10-19 12:00:00").build())
+        .is_none());
+    assert!(schedule
+        .banner_of(&pull("200").at("2021-03-10 12:00:00").build())
+        .is_none());
+}
+
+#[test]
+fn updates_happen_at_once_on_every_server() {
+    let schedule = BannerSchedule::builtin();
+    // made on the server in UTC-5
+    let at = |time| pull("301").uid(600000001).at(time).build();
+
+    // 1.4 is released at 10:00 in UTC+8, which is 21:00 of the day before in UTC-5
+    let banner = schedule.banner_of(&at("2021-03-16 22:00:00")).unwrap();
+    assert_eq!(banner.version, "1.4");
+    assert_eq!(banner.phase, 1);
+    // 1.3 ends before the update
+    let banner = schedule.banner_of(&at("2021-03-16 01:00:00")).unwrap();
+    assert_eq!(banner.version, "1.3");
+    assert!(schedule.banner_of(&at("2021-03-16 02:00:00")).is_none());
+    // the second phase starts at 18:00 in server time
+    let phase = |time| schedule.banner_of(&at(time)).unwrap().phase;
+    assert_eq!(phase("2021-04-06 15:00:00"), 1);
+    assert_eq!(phase("2021-04-06 18:00:00"), 2);
+}
+
+#[test]
+fn reports_rate_up_hits_per_banner() {
+    let hu_tao = item("10000046", "胡桃", ItemType::Character, Rarity::Five);
+    let qiqi = item("", "七七", ItemType::Character, Rarity::Five);
+    let xingqiu = item("10000025", "行秋", ItemType::Character, Rarity::Four);
+    let noelle = item("10000034", "诺艾尔", ItemType::Character, Rarity::Four);
+    let log = vec![
+        pull("301").at("2021-02-20 12:00:00").item(qiqi).build(),
+        pull("301").at("2021-03-05 12:00:00").build(),
+        pull("301").at("2021-03-05 13:00:00").item(xingqiu).build(),
+        pull("400").at("2021-03-06 12:00:00").item(noelle).build(),
+        pull("400").at("2021-03-07 12:00:00").item(hu_tao).build(),
+        pull("302").at("2021-03-07 13:00:00").build(),
+        pull("200").at("2021-03-07 14:00:00").build(),
+        pull("301").at("2021-06-01 12:00:00").build(),
+    ];
+    let report = Banners::new(&log);
+
+    assert_eq!(report.banners.len(), 3);
+    let keqing = &report.banners[0];
+    assert_eq!(keqing.banner.name, "鱼龙灯昼");
+    assert_eq!(keqing.len, 1);
+    assert_eq!(keqing.rate_up_rate(Rarity::Five), Some(0.0));
+    assert_eq!(keqing.rate_up_rate(Rarity::Four), None);
+
+    let hu_tao = &report.banners[2];
+    assert_eq!(hu_tao.len, 4);
+    assert_eq!(hu_tao.num_per_rarity[Rarity::Four], 2);
+    assert_eq!(hu_tao.rate_up_rate(Rarity::Five), Some(1.0));
+    assert_eq!(hu_tao.rate_up_rate(Rarity::Four), Some(0.5));
+
+    // banners are in order of their start, and rate-up four-stars of this weapon banner are
+    // unknown
+    let weapon = &report.banners[1];
+    assert_eq!(weapon.banner.kind, PoolKind::WeaponEvent);
+    assert_eq!(weapon.rate_up_rate(Rarity::Four), None);
+
+    assert_eq!(report.unknown, 1);
+    let mut output = vec![];
+    report.write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("1.3 赤团开时（第3期） 角色活动祈愿："));
+    assert!(output.contains("另有1抽不在已知的活动祈愿时间内"));
+}
+
+#[test]
+fn reports_four_star_rate_ups_of_weapon_banners() {
+    let log = vec![
+        pull("302")
+            .at("2021-03-20 12:00:00")
+            .item(item("11410", "暗巷闪光", ItemType::Weapon, Rarity::Four))
+            .build(),
+        pull("302")
+            .at("2021-03-20 12:01:00")
+            .item(item("15405", "弓藏", ItemType::Weapon, Rarity::Four))
+            .build(),
+    ];
+    let report = Banners::new(&log);
+
+    assert_eq!(report.banners.len(), 1);
+    assert_eq!(report.banners[0].rate_up_rate(Rarity::Four), Some(0.5));
+}
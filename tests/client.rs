@@ -0,0 +1,632 @@
+/// Integration tests of the API client against a fake server
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use genshin_gacha_exporter::{
+    cassette::CassetteMode,
+    client::{
+        ApiError, Client, ClientConfig, ProxyConfig, RecordError, RecordErrorKind,
+        ReqwestTransport, Transport, TransportFuture,
+    },
+    data_type::{ItemType, Pool, Rarity},
+};
+
+use reqwest::Url;
+
+use crate::common::{ids, pool, three_stars, FakePull, FakeServer, State, CHARACTER, WEAPON};
+
+/// The API error carried by `err`
+fn api_error(err: &anyhow::Error) -> Option<&ApiError> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<ApiError>())
+}
+
+#[tokio::test]
+async fn lists_pools() {
+    let server = FakeServer::start(State::default()).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+    let keys: Vec<&str> = client
+        .get_pools()
+        .iter()
+        .map(|pool| pool.key.as_str())
+        .collect();
+    assert_eq!(keys, ["100", "200", "301", "302"]);
+    assert_eq!(pool(&client, "301").name, "角色活动祈愿");
+}
+
+#[tokio::test]
+async fn pages_through_whole_log() {
+    let mut state = State::default();
+    state.logs.insert("301".to_owned(), three_stars(1, 45));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let log = client
+        .request_gacha_log(&pool(&client, "301"))
+        .await
+        .unwrap();
+    assert_eq!(ids(&log), (1..=45).collect::<Vec<_>>());
+    assert!(log.windows(2).all(|pair| pair[0].time < pair[1].time));
+    assert!(log.iter().all(|pull| pull.uid == 100000001));
+    assert!(log.iter().all(|pull| pull.gacha_type == "301"));
+    // each page continues from the last pull of the previous one until an empty page
+    let end_ids: Vec<u64> = server
+        .state
+        .lock()
+        .unwrap()
+        .requests
+        .iter()
+        .map(|(_, end_id)| *end_id)
+        .collect();
+    assert_eq!(end_ids, [0, 26, 6, 1]);
+}
+
+#[tokio::test]
+async fn stops_at_last_known_pull() {
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 45));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let log = client
+        .request_gacha_log_since(&pool(&client, "200"), Some(30))
+        .await
+        .unwrap();
+    assert_eq!(ids(&log), (31..=45).collect::<Vec<_>>());
+    assert_eq!(server.state.lock().unwrap().requests.len(), 1);
+}
+
+#[tokio::test]
+async fn empty_pool() {
+    let server = FakeServer::start(State::default()).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let log = client
+        .request_gacha_log(&pool(&client, "100"))
+        .await
+        .unwrap();
+    assert!(log.is_empty());
+    assert_eq!(server.state.lock().unwrap().requests.len(), 1);
+}
+
+#[tokio::test]
+async fn fetches_all_pools_concurrently() {
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 25));
+    state.logs.insert("301".to_owned(), three_stars(100, 3));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let requests: Vec<(&Pool, Option<u64>)> =
+        client.get_pools().iter().map(|pool| (pool, None)).collect();
+    let logs = client.request_gacha_logs_since(&requests).await.unwrap();
+    let lens: Vec<usize> = logs.iter().map(Vec::len).collect();
+    assert_eq!(lens, [0, 25, 3, 0]);
+}
+
+#[tokio::test]
+async fn recognizes_item_types() {
+    let mut state = State::default();
+    state.logs.insert(
+        "301".to_owned(),
+        vec![
+            FakePull {
+                id: 2,
+                time: "2021-03-02 12:00:00".to_owned(),
+                item_id: "1046".to_owned(),
+                name: "胡桃".to_owned(),
+                item_type: CHARACTER.to_owned(),
+                rank_type: 5,
+                count: "1".to_owned(),
+            },
+            FakePull {
+                id: 1,
+                time: "2021-03-01 12:00:00".to_owned(),
+                item_id: "15405".to_owned(),
+                name: "弓藏".to_owned(),
+                item_type: WEAPON.to_owned(),
+                rank_type: 4,
+                count: "1".to_owned(),
+            },
+        ],
+    );
+    state.logs.insert(
+        "200".to_owned(),
+        vec![
+            // type names of other languages
+            FakePull {
+                id: 4,
+                time: "2021-03-02 12:00:00".to_owned(),
+                item_id: "1022".to_owned(),
+                name: "Venti".to_owned(),
+                item_type: "Character".to_owned(),
+                rank_type: 5,
+                count: "1".to_owned(),
+            },
+            // unknown type names fall back to the id
+            FakePull {
+                id: 3,
+                time: "2021-03-01 12:00:00".to_owned(),
+                item_id: "11301".to_owned(),
+                name: "冷刃".to_owned(),
+                item_type: "".to_owned(),
+                rank_type: 3,
+                count: "1".to_owned(),
+            },
+        ],
+    );
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let log = client
+        .request_gacha_log(&pool(&client, "301"))
+        .await
+        .unwrap();
+    assert_eq!(log[0].item.name, "弓藏");
+    assert_eq!(log[0].item.item_type, ItemType::Weapon);
+    assert_eq!(log[0].item.rarity, Rarity::Four);
+    assert_eq!(log[1].item.item_id, "1046");
+    assert_eq!(log[1].item.item_type, ItemType::Character);
+    assert_eq!(log[1].item.rarity, Rarity::Five);
+
+    let log = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap();
+    assert_eq!(log[0].item.item_type, ItemType::Weapon);
+    assert_eq!(log[1].item.item_type, ItemType::Character);
+}
+
+#[tokio::test]
+async fn times_carry_server_offset() {
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 1));
+    let server = FakeServer::start(state).await;
+
+    for (region, time) in [
+        ("cn_gf01", "2021-03-01T00:01:00+08:00"),
+        ("os_usa", "2021-03-01T00:01:00-05:00"),
+        ("os_euro", "2021-03-01T00:01:00+01:00"),
+    ]
+    .iter()
+    {
+        let mut url = server.page_url();
+        let query: Vec<(String, String)> = url
+            .query_pairs()
+            .into_owned()
+            .map(|(key, value)| match key.as_str() {
+                "region" => (key, (*region).to_owned()),
+                _ => (key, value),
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(query);
+        let client = Client::new(url, &server.config()).await.unwrap();
+
+        let log = client
+            .request_gacha_log(&pool(&client, "200"))
+            .await
+            .unwrap();
+        assert_eq!(log[0].time.to_rfc3339(), *time);
+    }
+}
+
+#[tokio::test]
+async fn unknown_item_type_is_rejected() {
+    let mut state = State::default();
+    state.logs.insert(
+        "200".to_owned(),
+        vec![FakePull {
+            id: 1,
+            time: "2021-03-01 12:00:00".to_owned(),
+            item_id: "".to_owned(),
+            name: "原石".to_owned(),
+            item_type: "道具".to_owned(),
+            rank_type: 3,
+            count: "1".to_owned(),
+        }],
+    );
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let err = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("道具"));
+}
+
+/// Three pulls where the middle one has `rank_type` and `time` given
+fn with_bad_record(rank_type: u8, time: &str) -> Vec<FakePull> {
+    let mut log = three_stars(1, 3);
+    log[1].rank_type = rank_type;
+    log[1].time = time.to_owned();
+    log
+}
+
+#[tokio::test]
+async fn bad_record_is_reported() {
+    let mut state = State::default();
+    state
+        .logs
+        .insert("200".to_owned(), with_bad_record(6, "2021-03-01 00:02:00"));
+    state
+        .logs
+        .insert("302".to_owned(), with_bad_record(3, "2021-03-01 25:00:00"));
+    let mut log = three_stars(1, 3);
+    log[1].count = "ten".to_owned();
+    state.logs.insert("100".to_owned(), log);
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let err = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap_err();
+    let record_error = err.downcast_ref::<RecordError>().unwrap();
+    assert_eq!(record_error.kind, RecordErrorKind::Rarity("6".to_owned()));
+    assert!(record_error.record.contains(r#""id":"2""#));
+
+    let err = client
+        .request_gacha_log(&pool(&client, "302"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RecordError>().unwrap().kind,
+        RecordErrorKind::Time("2021-03-01 25:00:00".to_owned())
+    );
+
+    // an odd count fails only the record, not the page
+    let err = client
+        .request_gacha_log(&pool(&client, "100"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RecordError>().unwrap().kind,
+        RecordErrorKind::Count("ten".to_owned())
+    );
+}
+
+#[tokio::test]
+async fn skips_bad_records() {
+    let mut state = State::default();
+    state
+        .logs
+        .insert("200".to_owned(), with_bad_record(3, "yesterday"));
+    let mut log = three_stars(1, 3);
+    log[1].count = "ten".to_owned();
+    state.logs.insert("100".to_owned(), log);
+    let server = FakeServer::start(state).await;
+    let config = ClientConfig {
+        skip_bad_records: true,
+        ..server.config()
+    };
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+
+    let log = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap();
+    assert_eq!(ids(&log), [1, 3]);
+    let log = client
+        .request_gacha_log(&pool(&client, "100"))
+        .await
+        .unwrap();
+    assert_eq!(ids(&log), [1, 3]);
+}
+
+#[tokio::test]
+async fn url_without_host_is_rejected() {
+    let server = FakeServer::start(State::default()).await;
+    let url = Url::parse(&format!(
+        "unix:/event/gacha_info/api/getGachaLog?{}",
+        server.page_url().query().unwrap()
+    ))
+    .unwrap();
+    assert!(Client::new(url, &server.config()).await.is_err());
+}
+
+#[tokio::test]
+async fn expired_authkey() {
+    let mut state = State::default();
+    state.injected.push_back(Some(-101));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let err = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap_err();
+    assert_eq!(api_error(&err), Some(&ApiError::AuthkeyTimeout));
+    // not worth retrying
+    assert_eq!(server.state.lock().unwrap().requests.len(), 1);
+}
+
+#[tokio::test]
+async fn null_data_is_an_error() {
+    let mut state = State::default();
+    state.injected.push_back(Some(0));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let err = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap_err();
+    assert_eq!(api_error(&err), Some(&ApiError::MissingData));
+}
+
+#[tokio::test]
+async fn retries_when_visiting_too_frequently() {
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 5));
+    state.injected.push_back(Some(-110));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let log = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 5);
+    let end_ids: Vec<u64> = server
+        .state
+        .lock()
+        .unwrap()
+        .requests
+        .iter()
+        .map(|(_, end_id)| *end_id)
+        .collect();
+    assert_eq!(end_ids, [0, 0, 1]);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let mut state = State::default();
+    state.injected.extend(vec![Some(-110); 3]);
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let err = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap_err();
+    assert_eq!(api_error(&err), Some(&ApiError::VisitTooFrequently));
+    assert_eq!(server.state.lock().unwrap().requests.len(), 3);
+}
+
+#[tokio::test]
+async fn resumes_from_checkpoint() {
+    let checkpoint_dir = tempfile::tempdir().unwrap();
+    let mut state = State::default();
+    state.logs.insert("301".to_owned(), three_stars(1, 45));
+    // the second page fails
+    state.injected.extend(vec![None, Some(-100)]);
+    let server = FakeServer::start(state).await;
+    let mut config = server.config();
+    config.checkpoint_dir = Some(checkpoint_dir.path().to_owned());
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+
+    let err = client
+        .request_gacha_log(&pool(&client, "301"))
+        .await
+        .unwrap_err();
+    assert_eq!(api_error(&err), Some(&ApiError::InvalidAuthkey));
+    assert!(checkpoint_dir.path().join("301.json").exists());
+
+    // new pulls are made before the next run
+    {
+        let mut state = server.state.lock().unwrap();
+        let log = state.logs.get_mut("301").unwrap();
+        log.splice(0..0, three_stars(46, 3));
+        state.requests.clear();
+    }
+    let log = client
+        .request_gacha_log(&pool(&client, "301"))
+        .await
+        .unwrap();
+    assert_eq!(ids(&log), (1..=48).collect::<Vec<_>>());
+    // the first page is not requested again
+    let end_ids: Vec<u64> = server
+        .state
+        .lock()
+        .unwrap()
+        .requests
+        .iter()
+        .map(|(_, end_id)| *end_id)
+        .collect();
+    assert_eq!(end_ids, [0, 26, 6, 1]);
+    assert!(!checkpoint_dir.path().join("301.json").exists());
+}
+
+#[tokio::test]
+async fn replays_recorded_traffic() {
+    let cassette_dir = tempfile::tempdir().unwrap();
+    let cassette_path = cassette_dir.path().join("cassette.json");
+    let mut state = State::default();
+    state.logs.insert("301".to_owned(), three_stars(1, 25));
+    let server = FakeServer::start(state).await;
+
+    let mut config = server.config();
+    config.cassette = Some(CassetteMode::Record(cassette_path.clone()));
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+    let recorded = client
+        .request_gacha_log(&pool(&client, "301"))
+        .await
+        .unwrap();
+    let cassette = std::fs::read_to_string(&cassette_path).unwrap();
+    assert!(!cassette.contains("fake-authkey"));
+
+    // the server no longer has the log, and anything reaching it fails
+    {
+        let mut state = server.state.lock().unwrap();
+        state.logs.clear();
+        state.injected.extend(vec![Some(-101); 10]);
+        state.requests.clear();
+    }
+    config.cassette = Some(CassetteMode::Replay(cassette_path));
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+    let replayed = client
+        .request_gacha_log(&pool(&client, "301"))
+        .await
+        .unwrap();
+    assert_eq!(ids(&replayed), ids(&recorded));
+    assert!(server.state.lock().unwrap().requests.is_empty());
+}
+
+/// A transport counting the requests passing through
+#[derive(Debug, Default)]
+struct CountingTransport {
+    inner: ReqwestTransport,
+    count: Arc<AtomicUsize>,
+}
+
+impl Transport for CountingTransport {
+    fn get<'a>(&'a self, url: &'a Url) -> TransportFuture<'a> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        self.inner.get(url)
+    }
+}
+
+#[tokio::test]
+async fn requests_go_through_transport() {
+    let mut state = State::default();
+    state.logs.insert("302".to_owned(), three_stars(1, 5));
+    let server = FakeServer::start(state).await;
+    let transport = CountingTransport::default();
+    let count = transport.count.clone();
+    let client = Client::with_transport(server.page_url(), &server.config(), Box::new(transport))
+        .await
+        .unwrap();
+    // the pool list
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    client
+        .request_gacha_log(&pool(&client, "302"))
+        .await
+        .unwrap();
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn goes_through_proxy() {
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 5));
+    // the fake server also acts as the proxy, hosts behind it are unreachable otherwise
+    let server = FakeServer::start(state).await;
+    let mut url = server.page_url();
+    url.set_host(Some("gacha.invalid")).unwrap();
+    url.set_port(None).unwrap();
+    let mut config = server.config();
+    config.proxy = ProxyConfig::Url(format!("http://{}", server.addr));
+    let client = Client::new(url, &config).await.unwrap();
+
+    let log = client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 5);
+}
+
+#[tokio::test]
+async fn rejects_malformed_root_certificate() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ca.pem");
+    std::fs::write(&path, "-----BEGIN CERTIFICATE-----\nnot base64\n").unwrap();
+    assert!(ReqwestTransport::with_config(&ProxyConfig::None, &[path]).is_err());
+}
+
+#[tokio::test]
+async fn probes_builtin_pools_without_config_list() {
+    let mut state = State {
+        config_list: false,
+        ..State::default()
+    };
+    state.logs.insert("200".to_owned(), three_stars(1, 3));
+    state.logs.insert("500".to_owned(), three_stars(10, 2));
+    let server = FakeServer::start(state).await;
+    let client = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap();
+
+    let names: Vec<(&str, &str)> = client
+        .get_pools()
+        .iter()
+        .map(|pool| (pool.key.as_str(), pool.name.as_str()))
+        .collect();
+    assert_eq!(names, [("200", "常驻祈愿"), ("500", "集录祈愿")]);
+    // one single-item request per built-in pool
+    assert_eq!(server.state.lock().unwrap().requests.len(), 6);
+    let log = client
+        .request_gacha_log(&pool(&client, "500"))
+        .await
+        .unwrap();
+    assert_eq!(ids(&log), [10, 11]);
+}
+
+#[tokio::test]
+async fn pool_list_errors_are_not_hidden_by_probing() {
+    let state = State {
+        config_list_error: Some(-101),
+        ..State::default()
+    };
+    let server = FakeServer::start(state).await;
+    let err = Client::new(server.page_url(), &server.config())
+        .await
+        .unwrap_err();
+    assert_eq!(api_error(&err), Some(&ApiError::AuthkeyTimeout));
+    assert!(server.state.lock().unwrap().requests.is_empty());
+}
+
+#[tokio::test]
+async fn probes_configured_pools() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pools.json");
+    std::fs::write(
+        &path,
+        r#"[{"key": "301", "names": {"en-us": "Character Event Wish"}}]"#,
+    )
+    .unwrap();
+    let mut state = State {
+        config_list: false,
+        ..State::default()
+    };
+    state.logs.insert("200".to_owned(), three_stars(1, 3));
+    state.logs.insert("301".to_owned(), three_stars(10, 2));
+    let server = FakeServer::start(state).await;
+    let mut config = server.config();
+    config.pool_catalogue = Some(path);
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+
+    let pools = client.get_pools();
+    assert_eq!(pools.len(), 1);
+    // names fall back to English
+    assert_eq!(pools[0].name, "Character Event Wish");
+}
@@ -0,0 +1,345 @@
+#![allow(dead_code)]
+// every test crate uses its own part of the helpers
+
+/// An in-process fake of the miHoYo API server used by integration tests, along with
+/// builders of the records tests work with
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{NaiveDateTime, TimeZone};
+use genshin_gacha_exporter::{
+    client::{Client, ClientConfig, ProxyConfig},
+    data_type::{region_offset, uid_region, Item, ItemType, Pool, Pull, Rarity},
+};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::Url;
+use serde_json::{json, Value};
+
+/// Uid of the account tests play as, on a server in UTC+8
+pub const UID: usize = 100000001;
+/// Region used by the fake server
+pub const REGION: &str = "cn_gf01";
+/// Language used by the fake server
+pub const LANG: &str = "zh-cn";
+/// Item type the API uses for weapons
+pub const WEAPON: &str = "武器";
+/// Item type the API uses for characters
+pub const CHARACTER: &str = "角色";
+
+/// A pull stored by the fake server
+#[derive(Debug, Clone)]
+pub struct FakePull {
+    pub id: u64,
+    pub time: String,
+    pub item_id: String,
+    pub name: String,
+    pub item_type: String,
+    pub rank_type: u8,
+    pub count: String,
+}
+
+/// Everything the fake server knows, tests may change it at any time
+#[derive(Debug)]
+pub struct State {
+    pub uid: usize,
+    /// pools listed by `getConfigList`, as (id, key, name)
+    pub pools: Vec<(usize, String, String)>,
+    /// whether `getConfigList` exists
+    pub config_list: bool,
+    /// retcode `getConfigList` fails with, if any
+    pub config_list_error: Option<i32>,
+    /// pulls of each pool, newest first
+    pub logs: HashMap<String, Vec<FakePull>>,
+    /// what the next requests to `getGachaLog` get, `None` for the page as usual and
+    /// `Some(retcode)` for an error instead, where retcode 0 means null data
+    pub injected: VecDeque<Option<i32>>,
+    /// `end_id` of every request to `getGachaLog`, in order
+    pub requests: Vec<(String, u64)>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            uid: UID,
+            pools: vec![
+                (100, "100".to_owned(), "新手祈愿".to_owned()),
+                (200, "200".to_owned(), "常驻祈愿".to_owned()),
+                (301, "301".to_owned(), "角色活动祈愿".to_owned()),
+                (302, "302".to_owned(), "武器活动祈愿".to_owned()),
+            ],
+            config_list: true,
+            config_list_error: None,
+            logs: HashMap::new(),
+            injected: VecDeque::new(),
+            requests: vec![],
+        }
+    }
+}
+
+/// `count` three-star pulls with ids from `first_id` on, one minute apart, newest first
+pub fn three_stars(first_id: u64, count: u64) -> Vec<FakePull> {
+    (first_id..first_id + count)
+        .rev()
+        .map(|id| FakePull {
+            id,
+            time: format!("2021-03-01 {:02}:{:02}:00", id / 60 % 24, id % 60),
+            item_id: "11301".to_owned(),
+            name: "冷刃".to_owned(),
+            item_type: WEAPON.to_owned(),
+            rank_type: 3,
+            count: "1".to_owned(),
+        })
+        .collect()
+}
+
+/// An item as the API describes it
+pub fn item(item_id: &str, name: &str, item_type: ItemType, rarity: Rarity) -> Item {
+    Item {
+        item_id: item_id.to_owned(),
+        name: name.to_owned(),
+        item_type,
+        rarity,
+    }
+}
+
+/// The three-star weapon most pulls get
+pub fn three_star() -> Item {
+    item("11301", "冷刃", ItemType::Weapon, Rarity::Three)
+}
+
+/// A pull under construction, see [`pull`]
+#[derive(Debug, Clone)]
+pub struct PullBuilder {
+    id: Option<u64>,
+    uid: usize,
+    gacha_type: String,
+    lang: String,
+    time: NaiveDateTime,
+    item: Item,
+}
+
+/// Start building a pull from pool `gacha_type`. Unless told otherwise, it is a three-star
+/// without id, made by [`UID`] in zh-cn at noon of 2021-03-01
+pub fn pull(gacha_type: &str) -> PullBuilder {
+    PullBuilder {
+        id: None,
+        uid: UID,
+        gacha_type: gacha_type.to_owned(),
+        lang: LANG.to_owned(),
+        time: NaiveDateTime::parse_from_str("2021-03-01 12:00:00", "%F %T").unwrap(),
+        item: three_star(),
+    }
+}
+
+impl PullBuilder {
+    pub fn id(mut self, id: u64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn uid(mut self, uid: usize) -> Self {
+        self.uid = uid;
+        self
+    }
+
+    pub fn lang(mut self, lang: &str) -> Self {
+        self.lang = lang.to_owned();
+        self
+    }
+
+    /// Made at `time` such as `2021-03-01 12:00:00`, in the time zone of the server
+    pub fn at(mut self, time: &str) -> Self {
+        self.time = NaiveDateTime::parse_from_str(time, "%F %T").unwrap();
+        self
+    }
+
+    pub fn item(mut self, item: Item) -> Self {
+        self.item = item;
+        self
+    }
+
+    pub fn build(self) -> Pull {
+        Pull {
+            id: self.id,
+            uid: self.uid,
+            gacha_type: self.gacha_type,
+            count: 1,
+            lang: self.lang,
+            time: region_offset(uid_region(self.uid))
+                .from_local_datetime(&self.time)
+                .unwrap(),
+            item: self.item,
+        }
+    }
+}
+
+/// Find the pool identified by `key` among the pools of `client`
+pub fn pool(client: &Client, key: &str) -> Pool {
+    client
+        .get_pools()
+        .iter()
+        .find(|pool| pool.key == key)
+        .cloned()
+        .unwrap()
+}
+
+/// Ids of the pulls in order
+pub fn ids(log: &[Pull]) -> Vec<u64> {
+    log.iter().map(|pull| pull.id.unwrap()).collect()
+}
+
+/// A running fake server
+pub struct FakeServer {
+    pub addr: SocketAddr,
+    pub state: Arc<Mutex<State>>,
+}
+
+impl FakeServer {
+    /// Start a fake server on a random local port
+    pub async fn start(state: State) -> Self {
+        let state = Arc::new(Mutex::new(state));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(handle(&state, req)) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Self { addr, state }
+    }
+
+    /// Url of the gacha page as if captured from the game
+    pub fn page_url(&self) -> Url {
+        Url::parse_with_params(
+            &format!("http://{}/event/gacha_info/api/getGachaLog", self.addr),
+            &[
+                ("authkey_ver", "1"),
+                ("sign_type", "2"),
+                ("auth_appid", "webview_gacha"),
+                ("gacha_id", "fake"),
+                ("lang", LANG),
+                ("game_biz", "hk4e_cn"),
+                ("authkey", "fake-authkey"),
+                ("region", REGION),
+            ],
+        )
+        .unwrap()
+    }
+
+    /// Configuration of a client talking to this server, without checkpoint and delay
+    pub fn config(&self) -> ClientConfig {
+        ClientConfig {
+            requests_per_second: 1000.0,
+            max_retries: 2,
+            checkpoint_dir: None,
+            api_base_url: None,
+            cassette: None,
+            proxy: ProxyConfig::None,
+            root_certificates: vec![],
+            pool_catalogue: None,
+            skip_bad_records: false,
+        }
+    }
+}
+
+/// Wrap `data` as an API response
+fn api_response(retcode: i32, data: Value) -> Response<Body> {
+    let body = json!({
+        "retcode": retcode,
+        "message": if retcode == 0 { "OK" } else { "error" },
+        "data": data,
+    });
+    Response::new(Body::from(body.to_string()))
+}
+
+fn handle(state: &Mutex<State>, req: Request<Body>) -> Response<Body> {
+    let mut state = state.lock().unwrap();
+    // requests through a proxy come with absolute urls
+    let query: HashMap<String, String> = Url::parse("http://localhost/")
+        .unwrap()
+        .join(&format!("?{}", req.uri().query().unwrap_or_default()))
+        .unwrap()
+        .query_pairs()
+        .into_owned()
+        .collect();
+    match req.uri().path() {
+        "/event/gacha_info/api/getConfigList" if state.config_list => {
+            if let Some(retcode) = state.config_list_error {
+                return api_response(retcode, Value::Null);
+            }
+            let list: Vec<Value> = state
+                .pools
+                .iter()
+                .map(|(id, key, name)| json!({"id": id.to_string(), "key": key, "name": name}))
+                .collect();
+            api_response(0, json!({"gacha_type_list": list, "region": REGION}))
+        }
+        "/event/gacha_info/api/getGachaLog" => {
+            let key = query.get("gacha_type").cloned().unwrap_or_default();
+            let end_id: u64 = query
+                .get("end_id")
+                .and_then(|id| id.parse().ok())
+                .unwrap_or(0);
+            let size: usize = query
+                .get("size")
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(6);
+            state.requests.push((key.clone(), end_id));
+            if let Some(Some(retcode)) = state.injected.pop_front() {
+                return api_response(retcode, Value::Null);
+            }
+            let uid = state.uid;
+            let list: Vec<Value> = state
+                .logs
+                .get(&key)
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter(|pull| end_id == 0 || pull.id < end_id)
+                .take(size)
+                .map(|pull| {
+                    json!({
+                        "uid": uid.to_string(),
+                        "gacha_type": key,
+                        "item_id": pull.item_id,
+                        "count": pull.count,
+                        "time": pull.time,
+                        "name": pull.name,
+                        "lang": LANG,
+                        "item_type": pull.item_type,
+                        "rank_type": pull.rank_type.to_string(),
+                        "id": pull.id.to_string(),
+                    })
+                })
+                .collect();
+            api_response(
+                0,
+                json!({
+                    "page": query.get("page").cloned().unwrap_or_default(),
+                    "size": size.to_string(),
+                    "total": "0",
+                    "list": list,
+                    "region": REGION,
+                }),
+            )
+        }
+        _ => {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            response
+        }
+    }
+}
@@ -0,0 +1,138 @@
+/// Integration tests of the local database against a fake server
+mod common;
+
+use genshin_gacha_exporter::{client::Client, database::Database};
+
+use crate::common::{ids, pool, three_stars, FakeServer, State};
+
+#[tokio::test]
+async fn sync_ignores_checkpoints_of_other_fetches() {
+    let checkpoint_dir = tempfile::tempdir().unwrap();
+    let database_dir = tempfile::tempdir().unwrap();
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 30));
+    let server = FakeServer::start(state).await;
+    let mut config = server.config();
+    config.checkpoint_dir = Some(checkpoint_dir.path().to_owned());
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+    let database = Database::open(database_dir.path()).unwrap();
+    database.sync(&client, &pool(&client, "200")).await.unwrap();
+
+    // new pulls are made, and a fetch of the whole log is interrupted on the second page
+    {
+        let mut state = server.state.lock().unwrap();
+        let log = state.logs.get_mut("200").unwrap();
+        log.splice(0..0, three_stars(31, 15));
+        state.injected.extend(vec![None, Some(-100)]);
+    }
+    client
+        .request_gacha_log(&pool(&client, "200"))
+        .await
+        .unwrap_err();
+    assert!(checkpoint_dir.path().join("200.json").exists());
+
+    let log = database.sync(&client, &pool(&client, "200")).await.unwrap();
+    assert_eq!(ids(&log), (1..=45).collect::<Vec<_>>());
+    assert_eq!(ids(&database.load("200").unwrap()), ids(&log));
+    // the checkpoint of the other fetch is left for it
+    assert!(checkpoint_dir.path().join("200.json").exists());
+}
+
+#[tokio::test]
+async fn sync_drops_known_pulls_of_checkpoint() {
+    let checkpoint_dir = tempfile::tempdir().unwrap();
+    let database_dir = tempfile::tempdir().unwrap();
+    let mut state = State::default();
+    state.logs.insert("200".to_owned(), three_stars(1, 30));
+    let server = FakeServer::start(state).await;
+    let mut config = server.config();
+    config.checkpoint_dir = Some(checkpoint_dir.path().to_owned());
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+    let database = Database::open(database_dir.path()).unwrap();
+    database.sync(&client, &pool(&client, "200")).await.unwrap();
+
+    // a fetch of the whole log for the same account leaves pulls 45 to 26 in the
+    // checkpoint, 26 to 30 of which are already stored
+    {
+        let mut state = server.state.lock().unwrap();
+        let log = state.logs.get_mut("200").unwrap();
+        log.splice(0..0, three_stars(31, 15));
+        state.injected.extend(vec![None, Some(-100)]);
+    }
+    let mut account_config = server.config();
+    account_config.checkpoint_dir = Some(checkpoint_dir.path().join("100000001"));
+    let account_client = Client::new(server.page_url(), &account_config)
+        .await
+        .unwrap();
+    account_client
+        .request_gacha_log(&pool(&account_client, "200"))
+        .await
+        .unwrap_err();
+    assert!(checkpoint_dir
+        .path()
+        .join("100000001")
+        .join("200.json")
+        .exists());
+
+    let log = database.sync(&client, &pool(&client, "200")).await.unwrap();
+    assert_eq!(ids(&log), (1..=45).collect::<Vec<_>>());
+    assert_eq!(ids(&database.load("200").unwrap()), ids(&log));
+    assert!(!checkpoint_dir
+        .path()
+        .join("100000001")
+        .join("200.json")
+        .exists());
+}
+
+#[test]
+fn times_of_older_versions_are_moved_to_server_time() {
+    let database_dir = tempfile::tempdir().unwrap();
+    // stored on a machine in UTC+2 by an older version, for an account in America
+    std::fs::write(
+        database_dir.path().join("200.jsonl"),
+        r#"{"id":1,"uid":600000001,"gacha_type":"200","count":1,"lang":"en-us","time":"2021-03-01T12:00:00+02:00","item":{"item_id":"11301","name":"Cool Steel","item_type":"Weapon","rarity":"Three"}}"#,
+    )
+    .unwrap();
+    let database = Database::open(database_dir.path()).unwrap();
+
+    let log = database.load("200").unwrap();
+    assert_eq!(log[0].time.to_rfc3339(), "2021-03-01T12:00:00-05:00");
+}
+
+#[tokio::test]
+async fn skipped_records_are_fetched_again() {
+    let database_dir = tempfile::tempdir().unwrap();
+    let mut state = State::default();
+    let mut log = three_stars(1, 10);
+    // pull 5 cannot be understood by this version
+    log[5].rank_type = 6;
+    state.logs.insert("200".to_owned(), log);
+    let server = FakeServer::start(state).await;
+    let mut config = server.config();
+    config.skip_bad_records = true;
+    let client = Client::new(server.page_url(), &config).await.unwrap();
+    let database = Database::open(database_dir.path()).unwrap();
+
+    let log = database.sync(&client, &pool(&client, "200")).await.unwrap();
+    assert_eq!(ids(&log), vec![1, 2, 3, 4, 6, 7, 8, 9, 10]);
+
+    // newer pulls are made, and the record is understood by a newer version
+    {
+        let mut state = server.state.lock().unwrap();
+        let log = state.logs.get_mut("200").unwrap();
+        log[5].rank_type = 3;
+        log.splice(0..0, three_stars(11, 2));
+    }
+    let log = database.sync(&client, &pool(&client, "200")).await.unwrap();
+    assert_eq!(ids(&log), (1..=12).collect::<Vec<_>>());
+    assert_eq!(ids(&database.load("200").unwrap()), ids(&log));
+
+    // nothing is skipped any more, so the last stored pull is where fetching stops again
+    server.state.lock().unwrap().requests.clear();
+    let log = database.sync(&client, &pool(&client, "200")).await.unwrap();
+    assert_eq!(ids(&log), (1..=12).collect::<Vec<_>>());
+    assert_eq!(
+        server.state.lock().unwrap().requests,
+        [("200".to_owned(), 0)]
+    );
+}
@@ -0,0 +1,171 @@
+/// Tests of exporting to and importing from files
+mod common;
+
+use genshin_gacha_exporter::{
+    data_type::{ItemType, Pool, Rarity},
+    export::{export_csv, export_json, export_uigf},
+    import::{import_csv, import_json, import_uigf},
+};
+use serde_json::Value;
+
+use crate::common::{item, pull};
+
+#[test]
+fn csv_keeps_server_offset() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.csv");
+    let log = vec![
+        pull("200").id(1).at("2021-03-01 12:00:00").build(),
+        pull("200")
+            .id(2)
+            .uid(600000001)
+            .at("2021-03-01 12:00:00")
+            .build(),
+    ];
+    export_csv(&log, &path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("2021-03-01T12:00:00+08:00,冷刃"));
+    assert!(content.contains("2021-03-01T12:00:00-05:00,冷刃"));
+    let imported = import_csv(&path).unwrap();
+    let times: Vec<String> = imported.iter().map(|pull| pull.time.to_rfc3339()).collect();
+    assert_eq!(
+        times,
+        ["2021-03-01T12:00:00+08:00", "2021-03-01T12:00:00-05:00"]
+    );
+}
+
+#[test]
+fn csv_quotes_names_with_commas() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.csv");
+    let mut log = vec![pull("200").id(1).at("2021-03-01 12:00:00").build()];
+    log[0].item.name = "Sword, \"the\" Sharp".to_owned();
+    export_csv(&log, &path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains(",\"Sword, \"\"the\"\" Sharp\","));
+    let imported = import_csv(&path).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].item.name, "Sword, \"the\" Sharp");
+    assert_eq!(imported[0].gacha_type, "200");
+    assert_eq!(imported[0].item.item_id, "11301");
+}
+
+#[test]
+fn legacy_csv_times_are_in_server_time() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.csv");
+    std::fs::write(
+        &path,
+        "\u{feff}抽卡时间,抽卡结果,类型,稀有度,ID,UID\n\
+         2021-03-01 12:00:00,冷刃,武器,3\n\
+         2021-03-01 12:00:00,冷刃,武器,3,2,700000001\n",
+    )
+    .unwrap();
+
+    let imported = import_csv(&path).unwrap();
+    let times: Vec<String> = imported.iter().map(|pull| pull.time.to_rfc3339()).collect();
+    // the server is told by uid, and is assumed in UTC+8 without uid
+    assert_eq!(
+        times,
+        ["2021-03-01T12:00:00+08:00", "2021-03-01T12:00:00+01:00"]
+    );
+}
+
+#[test]
+fn json_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log.json");
+    let mut log = vec![
+        pull("200")
+            .id(1)
+            .uid(600000001)
+            .at("2021-03-01 12:00:00")
+            .build(),
+        pull("200")
+            .id(2)
+            .uid(600000001)
+            .at("2021-03-01 12:01:00")
+            .build(),
+    ];
+    log[1].id = None;
+    log[1].item.item_id = String::new();
+    let pools = vec![Pool {
+        id: 1,
+        key: "200".to_owned(),
+        name: "常驻祈愿".to_owned(),
+    }];
+    export_json(&pools, &log, &path).unwrap();
+
+    let imported = import_json(&path).unwrap();
+    assert_eq!(
+        serde_json::to_value(&imported).unwrap(),
+        serde_json::to_value(&log).unwrap()
+    );
+    assert_eq!(imported[0].time.to_rfc3339(), "2021-03-01T12:00:00-05:00");
+    // UIGF files are not taken for json exports
+    let uigf = dir.path().join("uigf.json");
+    std::fs::write(&uigf, r#"{"info": {"uid": "600000001"}, "list": []}"#).unwrap();
+    assert!(import_json(&uigf).is_err());
+}
+
+#[test]
+fn uigf_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("uigf.json");
+    let log = vec![
+        pull("301").id(1).uid(600000001).build(),
+        pull("400")
+            .id(2)
+            .uid(600000001)
+            .at("2021-03-01 12:01:00")
+            .item(item("10000046", "胡桃", ItemType::Character, Rarity::Five))
+            .build(),
+    ];
+    export_uigf(&log, &path).unwrap();
+
+    let document: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(document["info"]["uid"], "600000001");
+    let entry = &document["list"][1];
+    assert_eq!(entry["gacha_type"], "400");
+    // the two character event wishes share one type
+    assert_eq!(entry["uigf_gacha_type"], "301");
+    assert_eq!(entry["id"], "2");
+    assert_eq!(entry["item_id"], "10000046");
+    // in the time zone of the server
+    assert_eq!(entry["time"], "2021-03-01 12:01:00");
+
+    let imported = import_uigf(&path).unwrap();
+    assert_eq!(
+        serde_json::to_value(&imported).unwrap(),
+        serde_json::to_value(&log).unwrap()
+    );
+    assert_eq!(imported[1].time.to_rfc3339(), "2021-03-01T12:01:00-05:00");
+}
+
+#[test]
+fn uigf_without_gacha_type_falls_back_to_uigf_gacha_type() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("uigf.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "info": {"uid": "600000001", "lang": "en-us"},
+            "list": [
+                {"time": "2021-03-01 12:00:00", "name": "Cool Steel", "item_type": "Weapon",
+                 "rank_type": "3", "id": "1", "uigf_gacha_type": "301"}
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    let imported = import_uigf(&path).unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].gacha_type, "301");
+    assert_eq!(imported[0].id, Some(1));
+    assert_eq!(imported[0].count, 1);
+    assert_eq!(imported[0].lang, "en-us");
+    assert_eq!(imported[0].item.item_type, ItemType::Weapon);
+    assert_eq!(imported[0].time.to_rfc3339(), "2021-03-01T12:00:00-05:00");
+}
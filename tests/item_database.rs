@@ -0,0 +1,203 @@
+/// Tests of the item database and the reports using it
+mod common;
+
+use genshin_gacha_exporter::{
+    data_type::{ItemType, PoolKind, Rarity},
+    item_database::{Element, ItemDatabase, WeaponType},
+    report::{
+        guarantee::{Guarantee, Outcome},
+        Report,
+    },
+};
+
+use crate::common::{item, pull};
+
+#[test]
+fn looks_up_builtin_items() {
+    let database = ItemDatabase::builtin();
+
+    let hu_tao = database
+        .get(&item("10000046", "胡桃", ItemType::Character, Rarity::Five))
+        .unwrap();
+    assert_eq!(hu_tao.element, Some(Element::Pyro));
+    assert_eq!(hu_tao.weapon_type, WeaponType::Polearm);
+    assert_eq!(hu_tao.version, "1.3");
+    assert!(!hu_tao.standard);
+    assert_eq!(hu_tao.name("en-us"), "Hu Tao");
+    // unknown languages fall back to English
+    assert_eq!(hu_tao.name("it-it"), "Hu Tao");
+    assert_eq!(hu_tao.tag(), "火·长柄武器");
+
+    // short ids of item lists
+    let venti = database
+        .get(&item("1022", "温迪", ItemType::Character, Rarity::Five))
+        .unwrap();
+    assert_eq!(venti.item_id, "10000022");
+
+    // matched by name in any language without id
+    let rust = database
+        .get(&item("", "Rust", ItemType::Weapon, Rarity::Four))
+        .unwrap();
+    assert_eq!(rust.item_id, "15405");
+    assert_eq!(rust.element, None);
+    assert_eq!(rust.tag(), "弓");
+
+    assert!(database
+        .get(&item("", "原石", ItemType::Weapon, Rarity::Three))
+        .is_none());
+}
+
+#[test]
+fn looks_up_names_of_every_language() {
+    let database = ItemDatabase::builtin();
+    let id_of = |name, item_type, rarity| {
+        database
+            .get(&item("", name, item_type, rarity))
+            .map(|info| info.item_id.as_str())
+    };
+
+    assert_eq!(
+        id_of("ノエル", ItemType::Character, Rarity::Four),
+        Some("10000034")
+    );
+    assert_eq!(
+        id_of("Saccharose", ItemType::Character, Rarity::Four),
+        Some("10000043")
+    );
+    assert_eq!(
+        id_of("Кэ Цин", ItemType::Character, Rarity::Five),
+        Some("10000042")
+    );
+    assert_eq!(
+        id_of("천공의 날개", ItemType::Weapon, Rarity::Five),
+        Some("15501")
+    );
+    assert_eq!(
+        id_of("祭礼の剣", ItemType::Weapon, Rarity::Four),
+        Some("11403")
+    );
+    let bennett = database
+        .get(&item(
+            "10000032",
+            "班尼特",
+            ItemType::Character,
+            Rarity::Four,
+        ))
+        .unwrap();
+    assert_eq!(bennett.name("ko-kr"), "베넷");
+}
+
+#[test]
+fn local_file_updates_builtin_items() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("items.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "version": "1.5",
+            "items": [
+                {"item_id": "10000051", "item_type": "Character", "rarity": "Five",
+                 "element": "Cryo", "weapon_type": "Claymore", "version": "1.5",
+                 "standard": false, "names": {"zh-cn": "优菈", "en-us": "Eula"}},
+                {"item_id": "10000022", "item_type": "Character", "rarity": "Five",
+                 "element": "Anemo", "weapon_type": "Bow", "version": "1.0",
+                 "standard": true, "names": {"en-us": "Venti"}}
+            ]
+        }"#,
+    )
+    .unwrap();
+    let database = ItemDatabase::load(&path).unwrap();
+
+    assert_eq!(database.version, "1.5");
+    let eula = item("10000051", "优菈", ItemType::Character, Rarity::Five);
+    assert_eq!(database.get(&eula).unwrap().element, Some(Element::Cryo));
+    let venti = item("10000022", "温迪", ItemType::Character, Rarity::Five);
+    assert_eq!(database.is_standard(&venti), Some(true));
+    // bundled items are kept
+    let diluc = item("10000016", "迪卢克", ItemType::Character, Rarity::Five);
+    assert_eq!(database.is_standard(&diluc), Some(true));
+    let unknown = item("", "Unknown", ItemType::Character, Rarity::Five);
+    assert_eq!(database.is_standard(&unknown), None);
+}
+
+#[test]
+fn rejects_malformed_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("items.json");
+    std::fs::write(&path, r#"{"items": []}"#).unwrap();
+    assert!(ItemDatabase::load(&path).is_err());
+}
+
+#[test]
+fn standard_items_lose_the_fifty_fifty() {
+    let log = vec![
+        // Keqing is in the standard wish, but also the rate-up of her own banner
+        pull("301")
+            .at("2021-02-20 12:00:00")
+            .item(item("10000042", "刻晴", ItemType::Character, Rarity::Five))
+            .build(),
+        pull("301").at("2021-03-05 12:00:00").build(),
+        pull("301")
+            .at("2021-03-05 12:01:00")
+            .item(item(
+                "10000016",
+                "迪卢克",
+                ItemType::Character,
+                Rarity::Five,
+            ))
+            .build(),
+        pull("400").at("2021-03-06 12:00:00").build(),
+        pull("400")
+            .at("2021-03-06 12:01:00")
+            .item(item("10000046", "胡桃", ItemType::Character, Rarity::Five))
+            .build(),
+        // outside known banners, items not in the standard wish are taken as rate-up
+        pull("301")
+            .at("2021-06-01 12:00:00")
+            .item(item("", "Klee", ItemType::Character, Rarity::Five))
+            .build(),
+    ];
+    let guarantee = Guarantee::new(&log);
+
+    let banner = &guarantee.banners[PoolKind::CharacterEvent];
+    let outcomes: Vec<Outcome> = banner.pulls.iter().map(|(_, outcome)| *outcome).collect();
+    assert_eq!(
+        outcomes,
+        [
+            Outcome::Won,
+            Outcome::Lost,
+            Outcome::Guaranteed,
+            Outcome::Won
+        ]
+    );
+    assert_eq!(banner.guaranteed, Some(false));
+}
+
+#[test]
+fn unknown_items_leave_the_guarantee_unknown() {
+    let five_star = |time, item_id, name| {
+        pull("302")
+            .at(time)
+            .item(item(item_id, name, ItemType::Weapon, Rarity::Five))
+            .build()
+    };
+    let log = vec![
+        // a standard weapon, but the rate-up of the banner
+        five_star("2021-02-10 12:00:00", "13505", "和璞鸢"),
+        five_star("2021-02-11 12:00:00", "", "不知名的剑"),
+        five_star("2021-02-12 12:00:00", "11505", "磐岩结绿"),
+    ];
+    let guarantee = Guarantee::new(&log);
+
+    let banner = &guarantee.banners[PoolKind::WeaponEvent];
+    let outcomes: Vec<Outcome> = banner.pulls.iter().map(|(_, outcome)| *outcome).collect();
+    assert_eq!(outcomes, [Outcome::Won, Outcome::Unknown, Outcome::Unknown]);
+    assert_eq!(banner.guaranteed, Some(false));
+    let mut output = vec![];
+    guarantee.write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("不知名的剑： 未知"));
+
+    let guarantee = Guarantee::new(&log[..2]);
+    assert_eq!(guarantee.banners[PoolKind::WeaponEvent].guaranteed, None);
+}
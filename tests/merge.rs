@@ -0,0 +1,35 @@
+/// Tests of merging gacha logs from several sources
+mod common;
+
+use genshin_gacha_exporter::merge::merge;
+
+use crate::common::pull;
+
+#[test]
+fn pulls_with_different_ids_are_kept() {
+    let merged = merge(vec![
+        vec![pull("200").id(1).build()],
+        vec![pull("200").id(2).build()],
+    ]);
+    let ids: Vec<Option<u64>> = merged.pulls.iter().map(|pull| pull.id).collect();
+    assert_eq!(ids, [Some(1), Some(2)]);
+    assert_eq!(merged.overlaps, 0);
+    assert!(merged.conflicts.is_empty());
+}
+
+#[test]
+fn pulls_without_id_fall_back_to_time_and_name() {
+    let merged = merge(vec![
+        vec![pull("200").build(), pull("200").build()],
+        vec![
+            pull("200").id(1).build(),
+            pull("200").id(2).build(),
+            pull("200").id(3).build(),
+        ],
+    ]);
+    // the records with ids replace the ones without
+    let ids: Vec<Option<u64>> = merged.pulls.iter().map(|pull| pull.id).collect();
+    assert_eq!(ids, [Some(1), Some(2), Some(3)]);
+    assert_eq!(merged.overlaps, 2);
+    assert!(merged.conflicts.is_empty());
+}
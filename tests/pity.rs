@@ -0,0 +1,118 @@
+/// Tests of the pity report
+mod common;
+
+use genshin_gacha_exporter::{
+    data_type::{ItemType, PoolKind, Pull, Rarity},
+    report::{
+        pity::{Pity, PityForPool, PityForRarity},
+        Report,
+    },
+};
+
+use crate::common::{item, pull, three_star};
+
+/// Pulls from pool `gacha_type` getting items of `rarities` in order
+fn pulls(gacha_type: &str, rarities: &[Rarity]) -> Vec<Pull> {
+    rarities
+        .iter()
+        .map(|rarity| {
+            let item = match rarity {
+                Rarity::Five => item("10000016", "迪卢克", ItemType::Character, Rarity::Five),
+                Rarity::Four => item("10000014", "芭芭拉", ItemType::Character, Rarity::Four),
+                Rarity::Three => three_star(),
+            };
+            pull(gacha_type).item(item).build()
+        })
+        .collect()
+}
+
+fn pool(pity: &Pity, kind: PoolKind) -> &PityForPool {
+    pity.pools
+        .iter()
+        .find(|pool| pool.kind == Some(kind))
+        .unwrap()
+}
+
+/// Pity at which items of `rarity` are pulled
+fn pities(pool: &PityForPool, rarity: Rarity) -> Vec<usize> {
+    pool.pity_per_rarity[rarity]
+        .pulls
+        .iter()
+        .map(|(_, pity)| *pity)
+        .collect()
+}
+
+#[test]
+fn counters_are_kept_per_pool_kind() {
+    let log: Vec<Pull> = [
+        pulls("301", &[Rarity::Three, Rarity::Three]),
+        pulls("200", &[Rarity::Five]),
+        pulls("400", &[Rarity::Five]),
+        pulls("302", &[Rarity::Three]),
+    ]
+    .concat();
+    let pity = Pity::new(&log);
+
+    assert_eq!(pity.pools.len(), 3);
+    // the two character event wishes count towards the same pity
+    let character = pool(&pity, PoolKind::CharacterEvent);
+    assert_eq!(pities(character, Rarity::Five), [3]);
+    assert_eq!(character.pity_per_rarity[Rarity::Five].current, 0);
+    assert_eq!(pities(pool(&pity, PoolKind::Standard), Rarity::Five), [1]);
+    let weapon = pool(&pity, PoolKind::WeaponEvent);
+    assert!(pities(weapon, Rarity::Five).is_empty());
+    assert_eq!(weapon.pity_per_rarity[Rarity::Five].current, 1);
+}
+
+#[test]
+fn five_star_resets_four_star_counter() {
+    let log = pulls(
+        "200",
+        &[
+            Rarity::Three,
+            Rarity::Four,
+            Rarity::Three,
+            Rarity::Five,
+            Rarity::Three,
+            Rarity::Three,
+            Rarity::Four,
+        ],
+    );
+    let pity = Pity::new(&log);
+
+    let standard = pool(&pity, PoolKind::Standard);
+    assert_eq!(pities(standard, Rarity::Four), [2, 3]);
+    assert_eq!(pities(standard, Rarity::Five), [4]);
+    assert_eq!(standard.pity_per_rarity[Rarity::Four].current, 0);
+    assert_eq!(standard.pity_per_rarity[Rarity::Five].current, 3);
+}
+
+#[test]
+fn averages_and_medians() {
+    let with_pities = |pities: &[usize]| PityForRarity {
+        pulls: pities.iter().map(|pity| (three_star(), *pity)).collect(),
+        current: 0,
+    };
+
+    let odd = with_pities(&[30, 10, 20]);
+    assert_eq!(odd.average(), Some(20.0));
+    assert_eq!(odd.median(), Some(20.0));
+    let even = with_pities(&[70, 10, 30, 20]);
+    assert_eq!(even.average(), Some(32.5));
+    assert_eq!(even.median(), Some(25.0));
+    let none = with_pities(&[]);
+    assert_eq!(none.average(), None);
+    assert_eq!(none.median(), None);
+}
+
+#[test]
+fn weapon_pity_is_eighty() {
+    let pity = Pity::new(&pulls("302", &[Rarity::Three; 5]));
+
+    let mut output = vec![];
+    pity.write(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("武器活动祈愿抽出的五星："));
+    assert!(output.contains("目前已5抽未抽出五星，距离保底还有75抽"));
+    assert!(output.contains("目前已5抽未抽出四星，距离保底还有5抽"));
+}
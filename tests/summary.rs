@@ -0,0 +1,39 @@
+/// Tests of the summary report
+mod common;
+
+use genshin_gacha_exporter::report::{summary::Summary, Report};
+
+use crate::common::pull;
+
+fn render(summary: &Summary) -> String {
+    let mut output = vec![];
+    summary.write(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn droughts_are_shown_for_pools_sharing_pity() {
+    let summary = Summary::new(&[
+        pull("301").at("2021-03-01 00:00:00").build(),
+        pull("400").at("2021-03-01 00:01:00").build(),
+    ]);
+    assert!(!summary.mixed);
+    let output = render(&summary);
+    assert!(output.contains("目前2抽未抽出五星"));
+    assert!(output.contains("距离五星保底还有88抽"));
+}
+
+#[test]
+fn droughts_are_hidden_across_pools() {
+    let summary = Summary::new(&[
+        pull("200").at("2021-03-01 00:00:00").build(),
+        pull("301").at("2021-03-01 00:01:00").build(),
+        pull("302").at("2021-03-01 00:02:00").build(),
+    ]);
+    assert!(summary.mixed);
+    let output = render(&summary);
+    assert!(output.contains("你一共进行了3抽"));
+    assert!(!output.contains("未抽出五星"));
+    assert!(!output.contains("连续抽出"));
+    assert!(!output.contains("保底"));
+}